@@ -1,5 +1,6 @@
 // alphabet_analysis.rs
 
+use crate::key_generation::keyed_alphabet;
 use std::collections::HashSet;
 
 pub fn contains_keyword(alphabet: &str, keyword: &str) -> bool {
@@ -80,3 +81,77 @@ pub fn score_alphabet(alphabet: &str) -> f64 {
         0.0
     }
 }
+
+// How a mixed alphabet relates to the keyword construction of
+// `key_generation::keyed_alphabet`: the keyword's letters followed by the
+// rest of A-Z in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeywordDerivation {
+    pub keyword: String,
+    // Left rotation applied to the alphabet before the keyword was read.
+    pub rotation: usize,
+    // Letters after the keyword that break alphabetical order.
+    pub misplaced: usize,
+}
+
+impl KeywordDerivation {
+    pub fn is_exact(&self) -> bool {
+        self.misplaced == 0
+    }
+
+    pub fn alphabet(&self) -> String {
+        keyed_alphabet(&self.keyword)
+    }
+}
+
+// The closest keyword derivation of an alphabet over every rotation, since
+// a recovered tableau row is only known up to rotation. Each keyword letter
+// costs one and each misplaced letter after it two, so a short keyword
+// with a stray letter or two beats reading the errors into the keyword.
+pub fn keyword_derivation(alphabet: &str, max_keyword_length: usize) -> KeywordDerivation {
+    let letters: Vec<char> = alphabet.chars().collect();
+    let mut best: Option<(usize, KeywordDerivation)> = None;
+
+    for rotation in 0..letters.len().max(1) {
+        let mut rotated = letters.clone();
+        rotated.rotate_left(rotation);
+        for length in 0..=max_keyword_length.min(rotated.len()) {
+            let tail = &rotated[length..];
+            let misplaced = tail.len() - longest_increasing_subsequence(tail);
+            let cost = length + 2 * misplaced;
+            if best
+                .as_ref()
+                .is_none_or(|(best_cost, d)| (cost, misplaced) < (*best_cost, d.misplaced))
+            {
+                best = Some((
+                    cost,
+                    KeywordDerivation {
+                        keyword: rotated[..length].iter().collect(),
+                        rotation,
+                        misplaced,
+                    },
+                ));
+            }
+        }
+    }
+
+    best.map(|(_, derivation)| derivation)
+        .unwrap_or(KeywordDerivation {
+            keyword: String::new(),
+            rotation: 0,
+            misplaced: 0,
+        })
+}
+
+// Length of the longest (not necessarily contiguous) increasing subsequence.
+fn longest_increasing_subsequence(letters: &[char]) -> usize {
+    let mut lengths = vec![1; letters.len()];
+    for i in 0..letters.len() {
+        for j in 0..i {
+            if letters[j] < letters[i] {
+                lengths[i] = lengths[i].max(lengths[j] + 1);
+            }
+        }
+    }
+    lengths.into_iter().max().unwrap_or(0)
+}
@@ -0,0 +1,334 @@
+// annealing.rs
+
+use crate::decryption::{track_top_candidates, Candidate};
+use crate::hill_climb::{KeyMove, KeySpace, KeyedCipher};
+use crate::scorer::Scorer;
+use crate::seeding;
+use crate::substitution::{self, CipherFamily, Tableau};
+use rand::Rng;
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+// Something simulated annealing can walk: a random starting state, a random
+// neighbour of any state, and the plaintext a state produces.
+pub trait SearchSpace: Sync {
+    type State: Clone + Send;
+
+    // None when no state satisfies the space's constraints.
+    fn random_state<R: Rng>(&self, rng: &mut R) -> Option<Self::State>;
+
+    // None when the drawn move could not change the state.
+    fn propose<R: Rng>(&self, state: &Self::State, rng: &mut R) -> Option<Self::State>;
+
+    fn plaintext(&self, state: &Self::State) -> String;
+
+    fn candidate(&self, state: &Self::State, plaintext: String, score: f64) -> Candidate;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cooling {
+    // T = T0 * rate^step.
+    Geometric { rate: f64 },
+    // T falls in a straight line from T0 to zero over the iterations.
+    Linear,
+}
+
+impl FromStr for Cooling {
+    type Err = String;
+
+    // `linear`, `geometric` (rate 0.9999) or `geometric:RATE`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "linear" => Ok(Cooling::Linear),
+            None if s == "geometric" => Ok(Cooling::Geometric { rate: 0.9999 }),
+            Some(("geometric", rate)) => rate
+                .parse()
+                .map(|rate| Cooling::Geometric { rate })
+                .map_err(|e| format!("invalid cooling rate '{}': {}", rate, e)),
+            _ => Err(format!("unknown cooling schedule '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnnealingSchedule {
+    pub initial_temperature: f64,
+    pub cooling: Cooling,
+    // Proposals per chain.
+    pub iterations: usize,
+    // Independent chains, run in parallel.
+    pub chains: usize,
+    // Each chain draws from its own stream of this seed.
+    pub seed: u64,
+}
+
+impl Default for AnnealingSchedule {
+    fn default() -> Self {
+        // Sized for per-n-gram scores, where one key letter moves the score
+        // by a few hundredths.
+        Self {
+            initial_temperature: 0.05,
+            cooling: Cooling::Geometric { rate: 0.9999 },
+            iterations: 50_000,
+            chains: 8,
+            seed: seeding::random_seed(),
+        }
+    }
+}
+
+impl AnnealingSchedule {
+    pub fn temperature(&self, step: usize) -> f64 {
+        let temperature = match self.cooling {
+            Cooling::Geometric { rate } => self.initial_temperature * rate.powf(step as f64),
+            Cooling::Linear => {
+                let remaining = 1.0 - step as f64 / self.iterations.max(1) as f64;
+                self.initial_temperature * remaining
+            }
+        };
+        temperature.max(f64::MIN_POSITIVE)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainResult<S> {
+    pub state: S,
+    pub plaintext: String,
+    pub score: f64,
+}
+
+// Runs one chain. Worse states are accepted with probability
+// exp(delta / T); `on_improvement` sees every new best state of the chain.
+pub fn anneal_chain<S, R, F>(
+    space: &S,
+    schedule: &AnnealingSchedule,
+    scorer: &dyn Scorer,
+    rng: &mut R,
+    mut on_improvement: F,
+) -> Option<ChainResult<S::State>>
+where
+    S: SearchSpace,
+    R: Rng,
+    F: FnMut(&ChainResult<S::State>),
+{
+    let mut current = space.random_state(rng)?;
+    let current_plaintext = space.plaintext(&current);
+    let mut current_score = scorer.score(&current_plaintext);
+
+    let mut best = ChainResult {
+        state: current.clone(),
+        plaintext: current_plaintext,
+        score: current_score,
+    };
+    on_improvement(&best);
+
+    for step in 0..schedule.iterations {
+        let Some(proposal) = space.propose(&current, rng) else {
+            continue;
+        };
+        let plaintext = space.plaintext(&proposal);
+        let score = scorer.score(&plaintext);
+        let delta = score - current_score;
+
+        if delta >= 0.0 || rng.gen::<f64>() < (delta / schedule.temperature(step)).exp() {
+            current = proposal;
+            current_score = score;
+
+            if current_score > best.score {
+                best = ChainResult {
+                    state: current.clone(),
+                    plaintext,
+                    score: current_score,
+                };
+                on_improvement(&best);
+            }
+        }
+    }
+
+    Some(best)
+}
+
+// Runs the schedule's chains in parallel. Each chain keeps its own top_n
+// heap of improvements and merges it into the shared heap when it finishes.
+// Candidates carry the schedule's seed, which replays the whole run.
+pub fn anneal<S: SearchSpace>(
+    space: &S,
+    schedule: &AnnealingSchedule,
+    scorer: &dyn Scorer,
+    top_candidates: &Mutex<BinaryHeap<Reverse<Candidate>>>,
+    top_n: usize,
+) {
+    (0..schedule.chains.max(1))
+        .into_par_iter()
+        .for_each(|chain| {
+            let mut rng = seeding::stream(schedule.seed, chain);
+            let mut local_heap = BinaryHeap::new();
+            anneal_chain(space, schedule, scorer, &mut rng, |result| {
+                let candidate =
+                    space.candidate(&result.state, result.plaintext.clone(), result.score);
+                track_top_candidates(&mut local_heap, candidate, top_n);
+            });
+
+            let mut global_heap = top_candidates
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            for Reverse(candidate) in local_heap {
+                track_top_candidates(&mut global_heap, candidate, top_n);
+            }
+        });
+}
+
+// Keys for a keyed cipher: periodic keys or monoalphabetic alphabets.
+pub struct KeySearch {
+    pub ciphertext: String,
+    pub cipher: KeyedCipher,
+    pub space: KeySpace,
+}
+
+impl SearchSpace for KeySearch {
+    type State = Vec<char>;
+
+    fn random_state<R: Rng>(&self, rng: &mut R) -> Option<Vec<char>> {
+        Some(self.space.random_key(rng))
+    }
+
+    fn propose<R: Rng>(&self, state: &Vec<char>, rng: &mut R) -> Option<Vec<char>> {
+        let mut proposal = state.clone();
+        let mv = KeyMove::ALL[rng.gen_range(0..KeyMove::ALL.len())];
+        self.space
+            .mutate(&mut proposal, mv, rng)
+            .then_some(proposal)
+    }
+
+    fn plaintext(&self, state: &Vec<char>) -> String {
+        let key: String = state.iter().collect();
+        self.cipher.decrypt(&self.ciphertext, &key)
+    }
+
+    fn candidate(&self, state: &Vec<char>, plaintext: String, score: f64) -> Candidate {
+        let alphabet = match &self.cipher {
+            KeyedCipher::Periodic { tableau, .. } => tableau.cipher_alphabet(),
+            KeyedCipher::Monoalphabetic => substitution::STRAIGHT_ALPHABET.to_string(),
+        };
+        Candidate::new(score, plaintext, 0, 0, 0, state.iter().collect(), alphabet)
+    }
+}
+
+// The keyed alphabet of a Quagmire III tableau under a fixed periodic key.
+pub struct AlphabetSearch {
+    pub ciphertext: String,
+    pub family: CipherFamily,
+    pub key: String,
+}
+
+impl SearchSpace for AlphabetSearch {
+    type State = Vec<char>;
+
+    fn random_state<R: Rng>(&self, rng: &mut R) -> Option<Vec<char>> {
+        Some(KeySpace::alphabet_permutation().random_key(rng))
+    }
+
+    fn propose<R: Rng>(&self, state: &Vec<char>, rng: &mut R) -> Option<Vec<char>> {
+        let mut proposal = state.clone();
+        let mv = KeyMove::ALL[rng.gen_range(0..KeyMove::ALL.len())];
+        KeySpace::alphabet_permutation()
+            .mutate(&mut proposal, mv, rng)
+            .then_some(proposal)
+    }
+
+    fn plaintext(&self, state: &Vec<char>) -> String {
+        let alphabet: String = state.iter().collect();
+        match Tableau::new("keyed", &alphabet, &alphabet) {
+            Ok(tableau) => {
+                substitution::periodic_decrypt(&self.ciphertext, &self.key, self.family, &tableau)
+            }
+            Err(_) => String::new(),
+        }
+    }
+
+    fn candidate(&self, state: &Vec<char>, plaintext: String, score: f64) -> Candidate {
+        Candidate::new(
+            score,
+            plaintext,
+            0,
+            0,
+            0,
+            self.key.clone(),
+            state.iter().collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corpus;
+    use crate::scorer::MonogramScorer;
+
+    #[test]
+    fn cooling_schedules_parse_and_fall() {
+        assert_eq!("linear".parse::<Cooling>(), Ok(Cooling::Linear));
+        assert_eq!(
+            "geometric:0.5".parse::<Cooling>(),
+            Ok(Cooling::Geometric { rate: 0.5 })
+        );
+        assert!("cubic".parse::<Cooling>().is_err());
+
+        let schedule = AnnealingSchedule {
+            initial_temperature: 2.0,
+            cooling: Cooling::Linear,
+            iterations: 4,
+            chains: 1,
+            seed: 0,
+        };
+        assert_eq!(schedule.temperature(0), 2.0);
+        assert_eq!(schedule.temperature(2), 1.0);
+        assert!(schedule.temperature(4) > 0.0);
+    }
+
+    #[test]
+    fn annealing_recovers_a_planted_periodic_key() {
+        let plaintext = &corpus::normalize(corpus::KRYPTOS_PLAINTEXT)[..300];
+        let search = KeySearch {
+            ciphertext: substitution::periodic_encrypt(
+                plaintext,
+                "ORBIT",
+                CipherFamily::Beaufort,
+                &Tableau::straight(),
+            ),
+            cipher: KeyedCipher::Periodic {
+                family: CipherFamily::Beaufort,
+                tableau: Box::new(Tableau::straight()),
+            },
+            space: KeySpace::periodic(5),
+        };
+        let schedule = AnnealingSchedule {
+            initial_temperature: 0.05,
+            cooling: Cooling::Geometric { rate: 0.999 },
+            iterations: 5_000,
+            chains: 2,
+            seed: 17,
+        };
+        let top_candidates = Mutex::new(BinaryHeap::new());
+        anneal(
+            &search,
+            &schedule,
+            &MonogramScorer::english(),
+            &top_candidates,
+            3,
+        );
+
+        let mut candidates: Vec<Candidate> = top_candidates
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|Reverse(candidate)| candidate)
+            .collect();
+        candidates.sort_by(|a, b| b.cmp(a));
+        assert_eq!(candidates.len(), 3);
+        assert_eq!(candidates[0].keyword, "ORBIT");
+        assert_eq!(candidates[0].plaintext, plaintext);
+    }
+}
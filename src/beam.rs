@@ -0,0 +1,306 @@
+// beam.rs
+
+use crate::hill_climb::{self, HillClimbing, KeySpace};
+use crate::layers::{CipherLayer, LayerBounds, LayerKind, LayerStack};
+use crate::scorer::{ChiSquaredScorer, DigraphIocScorer, IocScorer, PeriodicIocScorer, Scorer};
+use crate::scoring;
+use crate::seeding;
+use crate::slicing;
+use crate::substitution::{self, Tableau};
+use crate::transposition;
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone)]
+pub struct BeamSearch {
+    // Intermediate texts kept after each layer.
+    pub width: usize,
+    // Letter distribution the periodic keys are fitted to column by column.
+    pub reference: [f64; 26],
+    // Climbs for monoalphabetic layers, which cannot be enumerated.
+    pub climb: HillClimbing,
+}
+
+impl Default for BeamSearch {
+    fn default() -> Self {
+        Self {
+            width: 50,
+            reference: scoring::english_letter_frequencies(),
+            climb: HillClimbing {
+                iterations: 20_000,
+                patience: 2_000,
+                restarts: 4,
+                seed: seeding::random_seed(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BeamEntry {
+    pub stack: LayerStack,
+    pub text: String,
+    // The stage score: the statistic of the stage that produced the entry,
+    // or the search's scorer after the last layer.
+    pub score: f64,
+}
+
+// Takes the layers off in the order of `kinds`, keeping the best `width`
+// intermediate texts after each one. A stage is rated by what the next
+// layer needs rather than by n-grams, which only make sense once every
+// layer is off:
+//
+//   transposition, periodic next        periodic IoC (the columns return)
+//   transposition, monoalphabetic next  digraph IoC
+//   substitution, transposition next    chi-squared letter fit
+//   last layer                          the scorer
+//
+// Any other pairing falls back to the scorer. `on_stage` sees each stage's
+// name and beam. Returns the final beam, best first.
+pub fn beam_search<F>(
+    ciphertext: &str,
+    kinds: &[LayerKind],
+    bounds: &LayerBounds,
+    config: &BeamSearch,
+    scorer: &dyn Scorer,
+    mut on_stage: F,
+) -> Vec<BeamEntry>
+where
+    F: FnMut(usize, &str, &[BeamEntry]),
+{
+    let mut beam = vec![BeamEntry {
+        stack: LayerStack { layers: Vec::new() },
+        text: ciphertext.to_string(),
+        score: 0.0,
+    }];
+
+    for (stage, &kind) in kinds.iter().enumerate() {
+        let statistic = stage_statistic(kind, kinds.get(stage + 1).copied(), bounds, config);
+        let stage_scorer: &dyn Scorer = statistic.as_deref().unwrap_or(scorer);
+
+        let expanded: Vec<BeamEntry> = beam
+            .par_iter()
+            .flat_map_iter(|entry| {
+                expand(&entry.text, kind, bounds, config, stage_scorer)
+                    .into_iter()
+                    .map(|layer| {
+                        let text = layer.decrypt(&entry.text);
+                        let mut stack = entry.stack.clone();
+                        stack.layers.push(layer);
+                        BeamEntry {
+                            score: stage_scorer.score(&text),
+                            stack,
+                            text,
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        beam = prune(expanded, config.width.max(1));
+        on_stage(stage, &stage_scorer.name(), &beam);
+    }
+
+    beam
+}
+
+fn stage_statistic(
+    kind: LayerKind,
+    next: Option<LayerKind>,
+    bounds: &LayerBounds,
+    config: &BeamSearch,
+) -> Option<Box<dyn Scorer>> {
+    match (kind, next?) {
+        (LayerKind::Columnar | LayerKind::Periodic, LayerKind::Periodic) => Some(Box::new(
+            PeriodicIocScorer::new(IocScorer::ENGLISH, bounds.max_key_length),
+        )),
+        (LayerKind::Columnar, LayerKind::Monoalphabetic) => Some(Box::new(DigraphIocScorer)),
+        (LayerKind::Periodic | LayerKind::Monoalphabetic, LayerKind::Columnar) => {
+            Some(Box::new(ChiSquaredScorer::new(config.reference)))
+        }
+        _ => None,
+    }
+}
+
+// The layers of one kind worth trying on a text: the best of every column
+// order up to the maximum width, the column-by-column best periodic key for
+// each family, alphabet and period, and the distinct summits of a few
+// monoalphabetic climbs.
+fn expand(
+    text: &str,
+    kind: LayerKind,
+    bounds: &LayerBounds,
+    config: &BeamSearch,
+    scorer: &dyn Scorer,
+) -> Vec<CipherLayer> {
+    match kind {
+        LayerKind::Columnar => best_orders(text, bounds.max_width, config.width.max(1), scorer),
+        LayerKind::Periodic => {
+            let mut layers = Vec::new();
+            for &family in &bounds.families {
+                for alphabet in &bounds.alphabets {
+                    let Ok(tableau) = Tableau::new("keyed", alphabet, alphabet) else {
+                        continue;
+                    };
+                    for period in 1..=bounds.max_key_length.max(1) {
+                        let solution = slicing::solve_period(
+                            text,
+                            period,
+                            family,
+                            &tableau,
+                            &config.reference,
+                        );
+                        layers.push(CipherLayer::Periodic {
+                            family,
+                            alphabet: alphabet.clone(),
+                            key: solution.key_string(&tableau),
+                        });
+                    }
+                }
+            }
+            layers
+        }
+        LayerKind::Monoalphabetic => {
+            let results = hill_climb::hill_climb(
+                &KeySpace::alphabet_permutation(),
+                &config.climb,
+                |key| {
+                    substitution::monoalphabetic_substitution(
+                        text,
+                        key,
+                        substitution::STRAIGHT_ALPHABET,
+                    )
+                },
+                scorer,
+            );
+            let mut seen = HashSet::new();
+            results
+                .into_iter()
+                .filter(|result| seen.insert(result.key.clone()))
+                .map(|result| CipherLayer::Monoalphabetic { key: result.key })
+                .collect()
+        }
+    }
+}
+
+// The best `width` entries, keeping one stack per intermediate text.
+fn prune(mut entries: Vec<BeamEntry>, width: usize) -> Vec<BeamEntry> {
+    entries.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut seen = HashSet::new();
+    entries
+        .into_iter()
+        .filter(|entry| seen.insert(entry.text.clone()))
+        .take(width)
+        .collect()
+}
+
+// The `width` column orders of 2 to `max_width` columns whose decryptions
+// score best, one per distinct text. There are n! orders of width n, so they
+// are stepped through one at a time rather than collected. No entry can
+// place more than `width` texts in the pruned beam, so nothing is lost.
+fn best_orders(
+    text: &str,
+    max_width: usize,
+    width: usize,
+    scorer: &dyn Scorer,
+) -> Vec<CipherLayer> {
+    // (score, text, order), best first.
+    let mut best: Vec<(f64, String, Vec<usize>)> = Vec::new();
+    for n in 2..=max_width {
+        let mut order: Vec<usize> = (0..n).collect();
+        loop {
+            let decrypted = transposition::columnar_decrypt(text, &order);
+            let score = scorer.score(&decrypted);
+            let qualifies = best.len() < width || best.last().is_some_and(|worst| score > worst.0);
+            if qualifies && !best.iter().any(|(_, kept, _)| *kept == decrypted) {
+                let at = best.partition_point(|(kept, _, _)| *kept >= score);
+                best.insert(at, (score, decrypted, order.clone()));
+                best.truncate(width);
+            }
+            if !transposition::next_permutation(&mut order) {
+                break;
+            }
+        }
+    }
+    best.into_iter()
+        .map(|(_, _, order)| CipherLayer::Columnar { order })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corpus;
+    use crate::scorer::MonogramScorer;
+    use crate::substitution::{CipherFamily, STRAIGHT_ALPHABET};
+
+    #[test]
+    fn beam_search_takes_off_a_planted_transposition_and_key() {
+        let plaintext = &corpus::normalize(corpus::KRYPTOS_PLAINTEXT)[..300];
+        let substituted = substitution::periodic_encrypt(
+            plaintext,
+            "ORB",
+            CipherFamily::Vigenere,
+            &Tableau::straight(),
+        );
+        let order = vec![2, 0, 3, 1];
+        let ciphertext = transposition::apply_permutation(
+            &substituted,
+            &transposition::columnar_permutation(substituted.len(), &order),
+        );
+        assert_eq!(
+            transposition::columnar_decrypt(&ciphertext, &order),
+            substituted
+        );
+
+        let bounds = LayerBounds {
+            kinds: vec![LayerKind::Columnar, LayerKind::Periodic],
+            max_layers: 2,
+            max_width: 4,
+            max_key_length: 3,
+            families: vec![CipherFamily::Vigenere],
+            alphabets: vec![STRAIGHT_ALPHABET.to_string()],
+        };
+        let config = BeamSearch {
+            width: 10,
+            ..BeamSearch::default()
+        };
+        let mut stages = Vec::new();
+        let beam = beam_search(
+            &ciphertext,
+            &bounds.kinds,
+            &bounds,
+            &config,
+            &MonogramScorer::english(),
+            |stage, name, entries| stages.push((stage, name.to_string(), entries.len())),
+        );
+
+        assert_eq!(stages.len(), 2);
+        assert!(stages[0].1.starts_with("periodic-ioc"));
+        assert_eq!(beam[0].text, plaintext);
+        assert_eq!(
+            beam[0].stack.layers,
+            [
+                CipherLayer::Columnar { order },
+                CipherLayer::Periodic {
+                    family: CipherFamily::Vigenere,
+                    alphabet: STRAIGHT_ALPHABET.to_string(),
+                    key: "ORB".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn best_orders_step_through_every_width() {
+        let text = "ABCDEFGHIJKL";
+        let layers = best_orders(text, 4, 1000, &MonogramScorer::english());
+        // 2! + 3! + 4! orders, less those that decrypt to a text already kept.
+        assert!(layers.len() <= 32);
+        assert!(layers.contains(&CipherLayer::Columnar {
+            order: vec![3, 1, 0, 2]
+        }));
+        let texts: HashSet<String> = layers.iter().map(|layer| layer.decrypt(text)).collect();
+        assert_eq!(texts.len(), layers.len());
+    }
+}
@@ -0,0 +1,201 @@
+// checkpoint.rs
+
+use crate::decryption::Candidate;
+use crate::preprocessing::RemovalPattern;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// How far a sweep got: the removal hypothesis and alphabet it resumes at, its
+// counters and its top candidates. The fingerprint describes the sweep's
+// inputs, so a checkpoint is only picked up by the same sweep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepCheckpoint {
+    pub fingerprint: String,
+    pub removal: usize,
+    pub alphabet: usize,
+    pub progress: usize,
+    pub rejected: usize,
+    pub candidates: Vec<Candidate>,
+}
+
+impl SweepCheckpoint {
+    // Writes tab-separated lines to a temporary file and renames it over the
+    // old checkpoint, so an interruption never leaves a partial one. Scores
+    // are stored as raw bits to survive the round trip exactly.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut contents = String::from("# kryptos sweep checkpoint\n");
+        contents.push_str(&format!("fingerprint\t{}\n", self.fingerprint));
+        contents.push_str(&format!("position\t{}\t{}\n", self.removal, self.alphabet));
+        contents.push_str(&format!("progress\t{}\t{}\n", self.progress, self.rejected));
+        for candidate in &self.candidates {
+            let removal = candidate
+                .removal
+                .as_ref()
+                .map_or_else(|| "none".to_string(), |pattern| pattern.to_string());
+            contents.push_str(&format!(
+                "candidate\t{:016x}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                candidate.score.to_bits(),
+                candidate.transposition,
+                candidate.substitution,
+                candidate.alphabet_index,
+                candidate.keyword,
+                candidate.alphabet,
+                removal,
+                candidate.plaintext
+            ));
+        }
+
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, contents)?;
+        fs::rename(&temporary, path)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut checkpoint = Self {
+            fingerprint: String::new(),
+            removal: 0,
+            alphabet: 0,
+            progress: 0,
+            rejected: 0,
+            candidates: Vec::new(),
+        };
+
+        for (line_number, line) in contents.lines().enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", line_number + 1, reason),
+                )
+            };
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                ["fingerprint", fingerprint] => checkpoint.fingerprint = fingerprint.to_string(),
+                ["position", removal, alphabet] => {
+                    checkpoint.removal = removal.parse().map_err(|_| invalid("bad position"))?;
+                    checkpoint.alphabet = alphabet.parse().map_err(|_| invalid("bad position"))?;
+                }
+                ["progress", progress, rejected] => {
+                    checkpoint.progress = progress.parse().map_err(|_| invalid("bad progress"))?;
+                    checkpoint.rejected = rejected.parse().map_err(|_| invalid("bad progress"))?;
+                }
+                ["candidate", score, transposition, substitution, alphabet_index, keyword, alphabet, removal, plaintext] =>
+                {
+                    let bad = |_| invalid("bad candidate");
+                    let mut candidate = Candidate::new(
+                        f64::from_bits(u64::from_str_radix(score, 16).map_err(bad)?),
+                        plaintext.to_string(),
+                        transposition.parse().map_err(bad)?,
+                        substitution.parse().map_err(bad)?,
+                        alphabet_index.parse().map_err(bad)?,
+                        keyword.to_string(),
+                        alphabet.to_string(),
+                    );
+                    if *removal != "none" {
+                        candidate.removal =
+                            Some(removal.parse::<RemovalPattern>().map_err(|e| invalid(&e))?);
+                    }
+                    checkpoint.candidates.push(candidate);
+                }
+                _ => return Err(invalid(&format!("unexpected line '{}'", line))),
+            }
+        }
+
+        Ok(checkpoint)
+    }
+}
+
+// A 64-bit FNV-1a digest in hex, for fingerprinting inputs by content. It is
+// spelled out rather than taken from std so it stays the same across Rust
+// releases.
+pub fn digest(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+// The digest of a file, or of every file in a directory taken in name order,
+// or "unreadable".
+pub fn path_digest<P: AsRef<Path>>(path: P) -> String {
+    let path = path.as_ref();
+    if path.is_dir() {
+        let Ok(entries) = fs::read_dir(path) else {
+            return "unreadable".to_string();
+        };
+        let mut files: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .collect();
+        files.sort();
+        let mut contents = Vec::new();
+        for file in files {
+            contents.extend(file.file_name().unwrap_or_default().as_encoded_bytes());
+            contents.extend(fs::read(&file).unwrap_or_default());
+        }
+        return digest(&contents);
+    }
+    match fs::read(path) {
+        Ok(contents) => digest(&contents),
+        Err(_) => "unreadable".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let mut with_removal = Candidate::new(
+            0.1 + 0.2,
+            "EASTNORTHEAST".to_string(),
+            0,
+            1,
+            3,
+            "KRYPTOS".to_string(),
+            "KRYPTOSABCDEFGHIJLMNQUVWXZ".to_string(),
+        );
+        with_removal.removal = Some("every:7:0".parse().unwrap());
+        let mut with_indices = with_removal.clone();
+        with_indices.score = -1.0 / 3.0;
+        with_indices.removal = Some("at:5,12".parse().unwrap());
+        let plain = Candidate::new(
+            f64::MIN_POSITIVE,
+            "BERLINCLOCK".to_string(),
+            0,
+            1,
+            0,
+            "PALIMPSEST".to_string(),
+            "ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string(),
+        );
+        let checkpoint = SweepCheckpoint {
+            fingerprint: "ciphertext=0123 cribs=EAST@21 scorer=legacy:1".to_string(),
+            removal: 1,
+            alphabet: 64,
+            progress: 12_345,
+            rejected: 678,
+            candidates: vec![with_removal, with_indices, plain],
+        };
+
+        let path = std::env::temp_dir().join(format!("kryptos-checkpoint-{}", std::process::id()));
+        checkpoint.save(&path).unwrap();
+        let loaded = SweepCheckpoint::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), checkpoint);
+    }
+
+    #[test]
+    fn load_rejects_malformed_lines() {
+        let path = std::env::temp_dir().join(format!("kryptos-malformed-{}", std::process::id()));
+        fs::write(&path, "position\t1\n").unwrap();
+        let loaded = SweepCheckpoint::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
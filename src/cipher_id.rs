@@ -0,0 +1,462 @@
+// cipher_id.rs
+
+use crate::corpus;
+use crate::kasiski;
+use crate::seeding;
+use crate::statistics;
+use crate::substitution::{self, CipherFamily, Tableau, STRAIGHT_ALPHABET};
+use crate::transposition;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rayon::prelude::*;
+
+// The ciphers reference profiles are built for: everything the crate can
+// encrypt with, plus unenciphered and uniformly random text as anchors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CipherType {
+    Plaintext,
+    Random,
+    Monoalphabetic,
+    Columnar,
+    Route,
+    Vigenere,
+    Beaufort,
+    VariantBeaufort,
+    Quagmire1,
+    Quagmire2,
+    Quagmire3,
+    Quagmire4,
+}
+
+impl CipherType {
+    pub const ALL: [CipherType; 12] = [
+        CipherType::Plaintext,
+        CipherType::Random,
+        CipherType::Monoalphabetic,
+        CipherType::Columnar,
+        CipherType::Route,
+        CipherType::Vigenere,
+        CipherType::Beaufort,
+        CipherType::VariantBeaufort,
+        CipherType::Quagmire1,
+        CipherType::Quagmire2,
+        CipherType::Quagmire3,
+        CipherType::Quagmire4,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CipherType::Plaintext => "plaintext",
+            CipherType::Random => "random",
+            CipherType::Monoalphabetic => "monoalphabetic",
+            CipherType::Columnar => "columnar",
+            CipherType::Route => "route",
+            CipherType::Vigenere => "vigenere",
+            CipherType::Beaufort => "beaufort",
+            CipherType::VariantBeaufort => "variant-beaufort",
+            CipherType::Quagmire1 => "quagmire1",
+            CipherType::Quagmire2 => "quagmire2",
+            CipherType::Quagmire3 => "quagmire3",
+            CipherType::Quagmire4 => "quagmire4",
+        }
+    }
+
+    // Enciphers the plaintext under a random key of this type. Periodic keys
+    // and transposition widths are drawn from 3..=15, the range the period
+    // statistics look at.
+    pub fn encrypt<R: Rng>(&self, plaintext: &str, rng: &mut R) -> String {
+        let family = match self {
+            CipherType::Beaufort => CipherFamily::Beaufort,
+            CipherType::VariantBeaufort => CipherFamily::VariantBeaufort,
+            _ => CipherFamily::Vigenere,
+        };
+        let tableau = match self {
+            CipherType::Quagmire1 => Tableau::quagmire1(&random_keyword(rng)),
+            CipherType::Quagmire2 => Tableau::quagmire2(&random_keyword(rng)),
+            CipherType::Quagmire3 => Tableau::quagmire3(&random_keyword(rng)),
+            CipherType::Quagmire4 => Tableau::quagmire4(&random_keyword(rng), &random_keyword(rng)),
+            _ => Tableau::straight(),
+        };
+        let period = rng.gen_range(3..=15);
+
+        match self {
+            CipherType::Plaintext => plaintext.to_string(),
+            CipherType::Random => random_letters(plaintext.len(), rng),
+            CipherType::Monoalphabetic => {
+                let mut key: Vec<char> = STRAIGHT_ALPHABET.chars().collect();
+                key.shuffle(rng);
+                let key: String = key.into_iter().collect();
+                substitution::monoalphabetic_substitution(plaintext, &key, STRAIGHT_ALPHABET)
+            }
+            CipherType::Columnar => {
+                transposition::columnar_transposition(plaintext, &random_letters(period, rng))
+            }
+            CipherType::Route => {
+                transposition::route_transposition(plaintext, &random_letters(period, rng))
+            }
+            _ => substitution::periodic_encrypt(
+                plaintext,
+                &random_letters(period, rng),
+                family,
+                &tableau,
+            ),
+        }
+    }
+}
+
+// The ACA-style identification statistics. Digraph-based scores (LDI, SDD)
+// are measured against the reference text the profiles are built from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CipherFeatures {
+    pub ioc: f64,
+    // Highest mean column IoC over periods 1..=15.
+    pub max_periodic_ioc: f64,
+    // IoC of overlapping digraphs.
+    pub dic: f64,
+    // IoC of the non-overlapping digraphs starting at even positions.
+    pub edi: f64,
+    // Square root of the percentage of trigram positions that repeat.
+    pub lr: f64,
+    // Percentage of repeat spacings that are odd.
+    pub rod: f64,
+    // Mean log10 reference probability of each digraph.
+    pub ldi: f64,
+    // Mean log10 ratio of each digraph's reference probability to the
+    // product of its letters' probabilities.
+    pub sdd: f64,
+    pub even_length: bool,
+    pub has_j: bool,
+    pub has_x: bool,
+    pub has_z: bool,
+}
+
+pub const FEATURE_NAMES: [&str; 12] = [
+    "IC", "MIC", "DIC", "EDI", "LR", "ROD", "LDI", "SDD", "EVEN", "HAS_J", "HAS_X", "HAS_Z",
+];
+
+// Lower bounds on each feature's spread, so a statistic that barely varies
+// across the samples (the letter flags especially) cannot dominate the
+// distance.
+const MIN_STD_DEVS: [f64; 12] = [
+    0.001, 0.001, 0.0002, 0.0002, 0.5, 2.0, 0.05, 0.05, 0.25, 0.25, 0.25, 0.25,
+];
+
+const MAX_FEATURE_PERIOD: usize = 15;
+
+impl CipherFeatures {
+    pub fn values(&self) -> [f64; 12] {
+        let flag = |b: bool| if b { 1.0 } else { 0.0 };
+        [
+            self.ioc,
+            self.max_periodic_ioc,
+            self.dic,
+            self.edi,
+            self.lr,
+            self.rod,
+            self.ldi,
+            self.sdd,
+            flag(self.even_length),
+            flag(self.has_j),
+            flag(self.has_x),
+            flag(self.has_z),
+        ]
+    }
+}
+
+// Digraph and letter log probabilities of the reference text, add-one
+// smoothed so every digraph has a finite score.
+#[derive(Debug, Clone)]
+pub struct DigraphTable {
+    letter_log_probs: [f64; 26],
+    digraph_log_probs: Vec<f64>,
+}
+
+impl DigraphTable {
+    pub fn new(reference: &str) -> Self {
+        let letters = statistics::letters(reference);
+        let mut letter_counts = [1.0; 26];
+        let mut digraph_counts = vec![1.0; 26 * 26];
+        for &b in &letters {
+            letter_counts[(b - b'A') as usize] += 1.0;
+        }
+        for pair in letters.windows(2) {
+            digraph_counts[digraph_index(pair)] += 1.0;
+        }
+
+        let letter_total: f64 = letter_counts.iter().sum();
+        let digraph_total: f64 = digraph_counts.iter().sum();
+        Self {
+            letter_log_probs: letter_counts.map(|c| (c / letter_total).log10()),
+            digraph_log_probs: digraph_counts
+                .iter()
+                .map(|c| (c / digraph_total).log10())
+                .collect(),
+        }
+    }
+
+    fn ldi(&self, letters: &[u8]) -> f64 {
+        mean(
+            letters
+                .windows(2)
+                .map(|p| self.digraph_log_probs[digraph_index(p)]),
+        )
+    }
+
+    fn sdd(&self, letters: &[u8]) -> f64 {
+        mean(letters.windows(2).map(|p| {
+            self.digraph_log_probs[digraph_index(p)]
+                - self.letter_log_probs[(p[0] - b'A') as usize]
+                - self.letter_log_probs[(p[1] - b'A') as usize]
+        }))
+    }
+}
+
+pub fn features(text: &str, digraphs: &DigraphTable) -> CipherFeatures {
+    let letters = statistics::letters(text);
+    let letter_text = String::from_utf8_lossy(&letters).into_owned();
+
+    let max_periodic_ioc = (1..=MAX_FEATURE_PERIOD.min(letters.len() / 2).max(1))
+        .map(|period| statistics::periodic_ioc(&letter_text, period))
+        .fold(0.0, f64::max);
+
+    let even: Vec<usize> = letters.chunks_exact(2).map(digraph_index).collect();
+
+    let trigrams: Vec<&[u8]> = letters.windows(3).collect();
+    let repeated = trigrams
+        .iter()
+        .filter(|t| trigrams.iter().filter(|u| u == t).count() > 1)
+        .count();
+    let lr = if trigrams.is_empty() {
+        0.0
+    } else {
+        (100.0 * repeated as f64 / trigrams.len() as f64).sqrt()
+    };
+
+    let spacings: Vec<usize> = kasiski::find_repeats(&letter_text, 3)
+        .into_iter()
+        .flat_map(|r| r.spacings)
+        .collect();
+    let rod = if spacings.is_empty() {
+        0.0
+    } else {
+        100.0 * spacings.iter().filter(|s| *s % 2 == 1).count() as f64 / spacings.len() as f64
+    };
+
+    CipherFeatures {
+        ioc: statistics::index_of_coincidence(&letter_text),
+        max_periodic_ioc,
+        dic: statistics::digraph_ioc(&letter_text),
+        edi: symbol_ioc(&even, 26 * 26),
+        lr,
+        rod,
+        ldi: digraphs.ldi(&letters),
+        sdd: digraphs.sdd(&letters),
+        even_length: letters.len().is_multiple_of(2),
+        has_j: letters.contains(&b'J'),
+        has_x: letters.contains(&b'X'),
+        has_z: letters.contains(&b'Z'),
+    }
+}
+
+// The spread of each feature over texts enciphered with one cipher type.
+#[derive(Debug, Clone)]
+pub struct CipherProfile {
+    pub cipher: CipherType,
+    pub means: [f64; 12],
+    pub std_devs: [f64; 12],
+}
+
+impl CipherProfile {
+    // Root mean square of the per-feature z-scores.
+    pub fn distance(&self, features: &CipherFeatures) -> f64 {
+        let values = features.values();
+        let sum: f64 = (0..values.len())
+            .map(|i| {
+                let std_dev = self.std_devs[i].max(MIN_STD_DEVS[i]);
+                ((values[i] - self.means[i]) / std_dev).powi(2)
+            })
+            .sum();
+        (sum / values.len() as f64).sqrt()
+    }
+}
+
+pub struct Identifier {
+    digraphs: DigraphTable,
+    pub profiles: Vec<CipherProfile>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Identification {
+    pub cipher: CipherType,
+    pub distance: f64,
+}
+
+impl Identifier {
+    // Builds a profile per cipher type from `samples` random stretches of the
+    // reference text, each `length` letters long, enciphered under random
+    // keys drawn from `seed`.
+    pub fn new(reference: &str, length: usize, samples: usize, seed: u64) -> Result<Self, String> {
+        let letters = corpus::normalize(reference);
+        if letters.len() < length || length < 2 {
+            return Err(format!(
+                "reference text has {} letters, need at least {}",
+                letters.len(),
+                length.max(2)
+            ));
+        }
+        let digraphs = DigraphTable::new(&letters);
+
+        let profiles = CipherType::ALL
+            .par_iter()
+            .enumerate()
+            .map(|(index, &cipher)| {
+                let mut rng = seeding::stream(seed, index);
+                let values: Vec<[f64; 12]> = (0..samples.max(2))
+                    .map(|_| {
+                        let start = rng.gen_range(0..=letters.len() - length);
+                        let ciphertext = cipher.encrypt(&letters[start..start + length], &mut rng);
+                        features(&ciphertext, &digraphs).values()
+                    })
+                    .collect();
+                profile(cipher, &values)
+            })
+            .collect();
+
+        Ok(Self { digraphs, profiles })
+    }
+
+    pub fn features(&self, text: &str) -> CipherFeatures {
+        features(text, &self.digraphs)
+    }
+
+    // Every cipher type, closest profile first.
+    pub fn identify(&self, text: &str) -> Vec<Identification> {
+        let features = self.features(text);
+        let mut ranking: Vec<Identification> = self
+            .profiles
+            .iter()
+            .map(|profile| Identification {
+                cipher: profile.cipher,
+                distance: profile.distance(&features),
+            })
+            .collect();
+        ranking.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        ranking
+    }
+}
+
+fn profile(cipher: CipherType, values: &[[f64; 12]]) -> CipherProfile {
+    let n = values.len() as f64;
+    let mut means = [0.0; 12];
+    let mut std_devs = [0.0; 12];
+    for i in 0..12 {
+        means[i] = values.iter().map(|v| v[i]).sum::<f64>() / n;
+        let variance = values
+            .iter()
+            .map(|v| (v[i] - means[i]).powi(2))
+            .sum::<f64>()
+            / (n - 1.0);
+        std_devs[i] = variance.sqrt();
+    }
+    CipherProfile {
+        cipher,
+        means,
+        std_devs,
+    }
+}
+
+fn symbol_ioc(symbols: &[usize], alphabet_size: usize) -> f64 {
+    let n = symbols.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let mut counts = vec![0usize; alphabet_size];
+    for &s in symbols {
+        counts[s] += 1;
+    }
+    let coincidences: usize = counts.iter().map(|&c| c * c.saturating_sub(1)).sum();
+    coincidences as f64 / (n * (n - 1)) as f64
+}
+
+fn digraph_index(pair: &[u8]) -> usize {
+    (pair[0] - b'A') as usize * 26 + (pair[1] - b'A') as usize
+}
+
+fn mean<I: Iterator<Item = f64>>(values: I) -> f64 {
+    let (sum, count) = values.fold((0.0, 0usize), |(s, n), v| (s + v, n + 1));
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f64
+    }
+}
+
+fn random_letters<R: Rng>(length: usize, rng: &mut R) -> String {
+    (0..length)
+        .map(|_| (b'A' + rng.gen_range(0..26u8)) as char)
+        .collect()
+}
+
+fn random_keyword<R: Rng>(rng: &mut R) -> String {
+    let length = rng.gen_range(4..=10);
+    random_letters(length, rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transpositions_keep_the_letters() {
+        let plaintext = corpus::normalize(corpus::KRYPTOS_PLAINTEXT);
+        let plaintext = &plaintext[..97];
+        let mut rng = seeding::rng(5);
+        let mut sorted_plaintext: Vec<char> = plaintext.chars().collect();
+        sorted_plaintext.sort_unstable();
+        for cipher in [CipherType::Columnar, CipherType::Route] {
+            let ciphertext = cipher.encrypt(plaintext, &mut rng);
+            assert_ne!(ciphertext, plaintext);
+            let mut sorted: Vec<char> = ciphertext.chars().collect();
+            sorted.sort_unstable();
+            assert_eq!(sorted, sorted_plaintext);
+        }
+        assert_eq!(
+            CipherType::Plaintext.encrypt(plaintext, &mut rng),
+            plaintext
+        );
+    }
+
+    #[test]
+    fn identifies_plaintext_and_periodic_ciphers() {
+        let identifier = Identifier::new(corpus::KRYPTOS_PLAINTEXT, 97, 40, 1).unwrap();
+        let plaintext = corpus::normalize(corpus::KRYPTOS_PLAINTEXT);
+        let sample = &plaintext[200..297];
+        assert_eq!(identifier.identify(sample)[0].cipher, CipherType::Plaintext);
+
+        // The statistics cannot tell the periodic ciphers apart, only
+        // that the text is one of them.
+        let mut rng = seeding::rng(9);
+        let ciphertext = CipherType::Vigenere.encrypt(sample, &mut rng);
+        let best = identifier.identify(&ciphertext)[0].cipher;
+        assert!(!matches!(
+            best,
+            CipherType::Plaintext
+                | CipherType::Random
+                | CipherType::Monoalphabetic
+                | CipherType::Columnar
+                | CipherType::Route
+        ));
+    }
+
+    #[test]
+    fn profiles_are_reproducible_from_their_seed() {
+        let build = |seed| Identifier::new(corpus::KRYPTOS_PLAINTEXT, 60, 10, seed).unwrap();
+        let (a, b) = (build(4), build(4));
+        for (a, b) in a.profiles.iter().zip(&b.profiles) {
+            assert_eq!(a.cipher, b.cipher);
+            assert_eq!(a.means, b.means);
+            assert_eq!(a.std_devs, b.std_devs);
+        }
+        assert!(Identifier::new("too short", 60, 10, 4).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+// cli.rs
+
+use std::collections::{HashMap, HashSet};
+use std::process;
+use std::str::FromStr;
+
+// Command-line options in the form `<command> [--name value] [--switch]`.
+pub struct Options {
+    pub command: String,
+    values: HashMap<String, String>,
+    switches: HashSet<String>,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Self {
+        let mut args = args.iter().peekable();
+        let command = match args.peek() {
+            Some(arg) if !arg.starts_with("--") => args.next().unwrap().clone(),
+            _ => "sweep".to_string(),
+        };
+
+        let mut values = HashMap::new();
+        let mut switches = HashSet::new();
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                eprintln!("Unexpected argument: {}", arg);
+                process::exit(2);
+            };
+
+            if let Some((name, value)) = name.split_once('=') {
+                values.insert(name.to_string(), value.to_string());
+            } else if args.peek().is_some_and(|next| !next.starts_with("--")) {
+                values.insert(name.to_string(), args.next().unwrap().clone());
+            } else {
+                switches.insert(name.to_string());
+            }
+        }
+
+        Self {
+            command,
+            values,
+            switches,
+        }
+    }
+
+    // The inverse of `entries`, for replaying the options of a stored run.
+    pub fn from_entries(command: &str, entries: &[(String, String)]) -> Self {
+        let mut values = HashMap::new();
+        let mut switches = HashSet::new();
+        for (name, value) in entries {
+            if value.is_empty() {
+                switches.insert(name.clone());
+            } else {
+                values.insert(name.clone(), value.clone());
+            }
+        }
+        Self {
+            command: command.to_string(),
+            values,
+            switches,
+        }
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    // Every option given, switches with an empty value, sorted by name.
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries: Vec<(String, String)> = self
+            .values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .chain(
+                self.switches
+                    .iter()
+                    .map(|name| (name.clone(), String::new())),
+            )
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    pub fn switch(&self, name: &str) -> bool {
+        self.switches.contains(name)
+    }
+
+    pub fn parsed<T: FromStr>(&self, name: &str, default: T) -> T
+    where
+        T::Err: std::fmt::Display,
+    {
+        match self.value(name) {
+            Some(value) => value.parse().unwrap_or_else(|e| {
+                eprintln!("Invalid value for --{}: {} ({})", name, value, e);
+                process::exit(2);
+            }),
+            None => default,
+        }
+    }
+}
@@ -0,0 +1,336 @@
+// constraints.rs
+
+use crate::cribs::Crib;
+use crate::preprocessing::RemovalPattern;
+
+// Hard crib constraints: the ciphertext letter at each crib position must
+// decrypt to the crib letter. Candidates are checked position by position
+// and rejected at the first mismatch, before any full decryption or scoring.
+#[derive(Debug, Clone)]
+pub struct CribFilter {
+    checks: Vec<(usize, char, char)>,
+}
+
+impl CribFilter {
+    pub fn new(ciphertext: &str, cribs: &[Crib]) -> Self {
+        Self::with_removal(ciphertext, cribs, None)
+    }
+
+    // Crib positions refer to the full ciphertext; once a removal pattern has
+    // been applied they shift left, and cribs over removed letters drop out.
+    pub fn with_removal(
+        ciphertext: &str,
+        cribs: &[Crib],
+        removal: Option<&RemovalPattern>,
+    ) -> Self {
+        let cipher_chars: Vec<char> = ciphertext.chars().collect();
+        let mut checks = Vec::new();
+
+        for crib in cribs {
+            for (position, required) in crib.letters() {
+                let Some(&c) = cipher_chars.get(position) else {
+                    continue;
+                };
+                let mapped = match removal {
+                    Some(pattern) => pattern.map_position(position),
+                    None => Some(position),
+                };
+                if let Some(mapped) = mapped {
+                    checks.push((mapped, c, required));
+                }
+            }
+        }
+
+        Self { checks }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checks.is_empty()
+    }
+
+    // Generic check against any position-wise decryption.
+    pub fn admits_with<F>(&self, decrypt_at: F) -> bool
+    where
+        F: Fn(usize, char) -> Option<char>,
+    {
+        self.checks
+            .iter()
+            .all(|&(position, c, required)| decrypt_at(position, c) == Some(required))
+    }
+
+    // Precomputes, for one alphabet of `substitution::polyalphabetic_substitution`,
+    // which key letters can produce each crib letter. Returns None when no key
+    // at all can satisfy the cribs under this alphabet.
+    pub fn for_polyalphabetic(&self, alphabet: &str) -> Option<AlphabetConstraint> {
+        let alphabet_chars: Vec<char> = alphabet.chars().collect();
+        let alphabet_len = alphabet_chars.len();
+        let mut shifts = [0usize; 128];
+        for (i, &c) in alphabet_chars.iter().enumerate().rev() {
+            if c.is_ascii() {
+                shifts[c as usize] = i;
+            }
+        }
+
+        let mut checks = Vec::with_capacity(self.checks.len());
+        for &(position, c, required) in &self.checks {
+            let allowed = match alphabet.find(c) {
+                Some(pos) => (0..alphabet_len)
+                    .map(|shift| alphabet_chars[(pos + shift) % alphabet_len] == required)
+                    .collect(),
+                // Letters outside the alphabet pass through unchanged.
+                None if c == required => vec![true; alphabet_len],
+                None => return None,
+            };
+            checks.push((position, allowed));
+        }
+
+        if checks.iter().any(|(_, allowed)| !allowed.contains(&true)) {
+            return None;
+        }
+
+        Some(AlphabetConstraint { shifts, checks })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AlphabetConstraint {
+    shifts: [usize; 128],
+    checks: Vec<(usize, Vec<bool>)>,
+}
+
+impl AlphabetConstraint {
+    // Whether the key reproduces every crib letter, reading only the key
+    // letters that fall on crib positions.
+    pub fn admits(&self, key: &str) -> bool {
+        let key = key.as_bytes();
+        if key.is_empty() {
+            return false;
+        }
+
+        self.checks.iter().all(|(position, allowed)| {
+            let key_char = key[position % key.len()];
+            let shift = if key_char.is_ascii() {
+                self.shifts[key_char as usize]
+            } else {
+                0
+            };
+            allowed[shift]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::substitution::polyalphabetic_substitution;
+
+    // The sweep's base alphabet, which repeats G, H, I, J, L and N.
+    const ALPHABET: &str = "KRYPTOSABCDEFGHIJLNGHIJLMNQUVWXZ";
+
+    fn cribs() -> Vec<Crib> {
+        vec![Crib::new(3, "EAST"), Crib::new(13, "CLOCK")]
+    }
+
+    // What the pruning stands in for: decrypt everything, then compare the
+    // crib letters where they fall after the removal.
+    fn decrypts_to_cribs(
+        ciphertext: &str,
+        key: &str,
+        alphabet: &str,
+        removal: Option<&RemovalPattern>,
+    ) -> bool {
+        let (text, cribs) = match removal {
+            Some(pattern) => (pattern.apply(ciphertext), pattern.map_cribs(&cribs())),
+            None => (ciphertext.to_string(), cribs()),
+        };
+        let plaintext: Vec<char> = polyalphabetic_substitution(&text, key, alphabet)
+            .chars()
+            .collect();
+        let matched = cribs
+            .iter()
+            .flat_map(|crib| crib.letters())
+            .filter(|&(position, _)| position < plaintext.len())
+            .all(|(position, c)| plaintext[position] == c);
+        matched
+    }
+
+    fn admits(
+        ciphertext: &str,
+        key: &str,
+        alphabet: &str,
+        removal: Option<&RemovalPattern>,
+    ) -> bool {
+        CribFilter::with_removal(ciphertext, &cribs(), removal)
+            .for_polyalphabetic(alphabet)
+            .is_some_and(|constraint| constraint.admits(key))
+    }
+
+    // A ciphertext that decrypts under the key to the cribs, with '?' and
+    // Q between them, and with X wherever the removal strips a letter. With
+    // repeated letters only first occurrences can be reached, so some keys
+    // cannot produce some crib letters at all; those give None.
+    fn plant(
+        key: &str,
+        alphabet: &str,
+        removal: Option<&RemovalPattern>,
+        length: usize,
+    ) -> Option<String> {
+        let cribs = match removal {
+            Some(pattern) => pattern.map_cribs(&cribs()),
+            None => cribs(),
+        };
+        let key: Vec<char> = key.chars().collect();
+        let kept_length = (0..length)
+            .filter(|&i| !removal.is_some_and(|pattern| pattern.removes(i)))
+            .count();
+        let mut kept = Vec::new();
+        for i in 0..kept_length {
+            let crib_letter = cribs
+                .iter()
+                .flat_map(|crib| crib.letters())
+                .find(|&(position, _)| position == i)
+                .map(|(_, c)| c);
+            let plain = crib_letter.unwrap_or(if i % 5 == 0 { '?' } else { 'Q' });
+            let shift = alphabet.find(key[i % key.len()]).unwrap_or(0);
+            let cipher = alphabet.chars().find(|&c| {
+                let position = alphabet.find(c).unwrap();
+                alphabet.chars().nth((position + shift) % alphabet.len()) == Some(plain)
+            });
+            match (cipher, crib_letter) {
+                (Some(cipher), _) => kept.push(cipher),
+                (None, None) => kept.push(plain),
+                (None, Some(_)) => return None,
+            }
+        }
+
+        let mut kept = kept.into_iter();
+        Some(
+            (0..length)
+                .map(|i| match removal {
+                    Some(pattern) if pattern.removes(i) => 'X',
+                    _ => kept.next().unwrap(),
+                })
+                .collect(),
+        )
+    }
+
+    fn every_key(length: u32) -> impl Iterator<Item = String> {
+        (0..26u32.pow(length)).map(move |mut n| {
+            (0..length)
+                .map(|_| {
+                    let c = (b'A' + (n % 26) as u8) as char;
+                    n /= 26;
+                    c
+                })
+                .collect()
+        })
+    }
+
+    fn assert_agrees_on_every_key(
+        ciphertext: &str,
+        alphabet: &str,
+        removal: Option<&RemovalPattern>,
+    ) -> usize {
+        let mut admitted = 0;
+        for key in every_key(3) {
+            let expected = decrypts_to_cribs(ciphertext, &key, alphabet, removal);
+            assert_eq!(
+                admits(ciphertext, &key, alphabet, removal),
+                expected,
+                "key {} on {} under {:?}",
+                key,
+                ciphertext,
+                removal
+            );
+            admitted += usize::from(expected);
+        }
+        admitted
+    }
+
+    // The first three-letter key using one of the letters that can be
+    // planted, with its ciphertext.
+    fn planted(
+        letters: &str,
+        alphabet: &str,
+        removal: Option<&RemovalPattern>,
+        length: usize,
+    ) -> (String, String) {
+        every_key(3)
+            .filter(|key| key.chars().any(|c| letters.contains(c)))
+            .find_map(|key| Some((plant(&key, alphabet, removal, length)?, key)))
+            .unwrap()
+    }
+
+    #[test]
+    fn admits_matches_decryption_with_repeated_alphabet_letters() {
+        // G, H, I, J, L and N sit twice in the alphabet; only the first
+        // occurrence sets the shift.
+        let (ciphertext, key) = planted("GHIJLN", ALPHABET, None, 24);
+        assert!(decrypts_to_cribs(&ciphertext, &key, ALPHABET, None));
+        assert!(ciphertext.contains('?'));
+
+        let admitted = assert_agrees_on_every_key(&ciphertext, ALPHABET, None);
+        assert!((1..26 * 26 * 26).contains(&admitted));
+    }
+
+    #[test]
+    fn key_letters_missing_from_the_alphabet_shift_by_zero() {
+        // No Z in the alphabet, so Z keys like its first letter, A.
+        let alphabet = "ABCDEFGHIJKLMNOPQRSTUVWXY";
+        let ciphertext = plant("ADA", alphabet, None, 24).unwrap();
+        assert!(admits(&ciphertext, "ZDZ", alphabet, None));
+        assert!(decrypts_to_cribs(&ciphertext, "ZDZ", alphabet, None));
+        assert_agrees_on_every_key(&ciphertext, alphabet, None);
+    }
+
+    #[test]
+    fn non_alphabet_ciphertext_letters_pass_through() {
+        let (ciphertext, key) = planted("K", ALPHABET, None, 24);
+        let mut letters: Vec<char> = ciphertext.chars().collect();
+        // A '?' over a crib letter can never decrypt to it.
+        letters[4] = '?';
+        let ciphertext: String = letters.into_iter().collect();
+        assert!(!admits(&ciphertext, &key, ALPHABET, None));
+        assert_eq!(assert_agrees_on_every_key(&ciphertext, ALPHABET, None), 0);
+        assert!(CribFilter::new(&ciphertext, &cribs())
+            .for_polyalphabetic(ALPHABET)
+            .is_none());
+    }
+
+    #[test]
+    fn admits_matches_decryption_after_removals() {
+        for removal in [
+            RemovalPattern::EveryNth { n: 4, offset: 1 },
+            RemovalPattern::Indices(vec![0, 5, 14]),
+        ] {
+            let (ciphertext, key) = planted("GHIJLN", ALPHABET, Some(&removal), 26);
+            assert!(admits(&ciphertext, &key, ALPHABET, Some(&removal)));
+            // Read without the removal, the cribs sit over other letters.
+            assert!(!admits(&ciphertext, &key, ALPHABET, None));
+            let admitted = assert_agrees_on_every_key(&ciphertext, ALPHABET, Some(&removal));
+            assert!(admitted >= 1);
+        }
+    }
+
+    #[test]
+    fn a_key_that_contradicts_a_crib_is_rejected() {
+        let (ciphertext, key) = planted("GHIJLN", ALPHABET, None, 24);
+        assert!(admits(&ciphertext, &key, ALPHABET, None));
+        // Changing any key letter moves the crib letters it covers.
+        for i in 0..key.len() {
+            let mut other: Vec<u8> = key.clone().into_bytes();
+            other[i] = if other[i] == b'A' { b'B' } else { b'A' };
+            let other = String::from_utf8(other).unwrap();
+            assert!(!admits(&ciphertext, &other, ALPHABET, None), "{}", other);
+            assert!(
+                !decrypts_to_cribs(&ciphertext, &other, ALPHABET, None),
+                "{}",
+                other
+            );
+        }
+        assert!(!CribFilter::new(&ciphertext, &cribs())
+            .for_polyalphabetic(ALPHABET)
+            .unwrap()
+            .admits(""));
+    }
+}
@@ -0,0 +1,203 @@
+// corpus.rs
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+// Reduces text to the A-Z letters the scorers work on. Common accented
+// Latin letters are folded to their base letter and ß becomes SS.
+pub fn normalize(text: &str) -> String {
+    let mut letters = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_alphabetic() {
+            letters.push(c.to_ascii_uppercase());
+            continue;
+        }
+        match c {
+            'À'..='Å' | 'à'..='å' => letters.push('A'),
+            'Ç' | 'ç' => letters.push('C'),
+            'È'..='Ë' | 'è'..='ë' => letters.push('E'),
+            'Ì'..='Ï' | 'ì'..='ï' => letters.push('I'),
+            'Ñ' | 'ñ' => letters.push('N'),
+            'Ò'..='Ö' | 'ò'..='ö' | 'Ø' | 'ø' => letters.push('O'),
+            'Ù'..='Ü' | 'ù'..='ü' => letters.push('U'),
+            'Ý' | 'ý' | 'ÿ' => letters.push('Y'),
+            'ß' => letters.push_str("SS"),
+            _ => {}
+        }
+    }
+    letters
+}
+
+pub fn count_ngrams(letters: &str, n: usize, counts: &mut HashMap<String, u64>) {
+    if n == 0 {
+        return;
+    }
+    for window in letters.as_bytes().windows(n) {
+        // Normalized text is pure ASCII, so every window is valid UTF-8.
+        let ngram = std::str::from_utf8(window).unwrap();
+        *counts.entry(ngram.to_string()).or_insert(0) += 1;
+    }
+}
+
+// Splits text on anything that is not a letter and counts each normalized
+// word, for the word-segmentation model.
+pub fn count_words(text: &str, counts: &mut HashMap<String, u64>) {
+    for word in text.split(|c: char| !c.is_alphabetic()) {
+        let word = normalize(word);
+        if !word.is_empty() {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CorpusCounts {
+    pub files: usize,
+    pub letters: u64,
+    // ngrams[n - 1] holds the counts for n-grams of length n.
+    pub ngrams: Vec<HashMap<String, u64>>,
+    pub words: HashMap<String, u64>,
+}
+
+impl CorpusCounts {
+    pub fn new(max_n: usize) -> Self {
+        Self {
+            files: 0,
+            letters: 0,
+            ngrams: vec![HashMap::new(); max_n],
+            words: HashMap::new(),
+        }
+    }
+
+    pub fn add_text(&mut self, text: &str) {
+        let letters = normalize(text);
+        self.files += 1;
+        self.letters += letters.len() as u64;
+        for (i, counts) in self.ngrams.iter_mut().enumerate() {
+            count_ngrams(&letters, i + 1, counts);
+        }
+        count_words(text, &mut self.words);
+    }
+}
+
+// Every regular file below the directory, in a stable order.
+pub fn corpus_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+// Counts n-grams of every length up to max_n across all files below the
+// directory. Files are counted separately so no n-gram spans two books, and
+// are read lossily since older e-texts are often not valid UTF-8.
+pub fn count_corpus(dir: &Path, max_n: usize) -> io::Result<CorpusCounts> {
+    let mut counts = CorpusCounts::new(max_n);
+    for path in corpus_files(dir)? {
+        let bytes = fs::read(&path)?;
+        counts.add_text(&String::from_utf8_lossy(&bytes));
+    }
+    Ok(counts)
+}
+
+// Writes counts as `NGRAM COUNT` lines, most frequent first, in the format
+// `NgramModel::load` and `WordModel::load` read.
+pub fn write_counts(path: &Path, counts: &HashMap<String, u64>) -> io::Result<()> {
+    let mut entries: Vec<(&String, &u64)> = counts.iter().collect();
+    entries.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    for (ngram, count) in entries {
+        writeln!(writer, "{} {}", ngram, count)?;
+    }
+    writer.flush()
+}
+
+// The solved plaintext of K1 to K3, spelling mistakes included. It is the
+// fallback reference text when no corpus is given.
+pub const KRYPTOS_PLAINTEXT: &str = "\
+    Between subtle shading and the absence of light lies the nuance of iqlusion. It was \
+    totally invisible. Hows that possible? They used the earths magnetic field. x The \
+    information was gathered and transmitted undergruund to an unknown location. x Does \
+    Langley know about this? They should its buried out there somewhere. x Who knows the \
+    exact location? Only WW. This was his last message. x Thirty eight degrees fifty seven \
+    minutes six point five seconds north, seventy seven degrees eight minutes forty four \
+    seconds west. x Layer two. Slowly desparatly slowly the remains of passage debris that \
+    encumbered the lower part of the doorway was removed. With trembling hands I made a tiny \
+    breach in the upper left hand corner. And then widening the hole a little I inserted the \
+    candle and peered in. The hot air escaping from the chamber caused the flame to flicker \
+    but presently details of the room within emerged from the mist. x Can you see anything \
+    q?";
+
+// The normalized letters of every file below the directory, joined.
+pub fn corpus_letters(dir: &Path) -> io::Result<String> {
+    let mut letters = String::new();
+    for path in corpus_files(dir)? {
+        let bytes = fs::read(&path)?;
+        letters.push_str(&normalize(&String::from_utf8_lossy(&bytes)));
+    }
+    Ok(letters)
+}
+
+pub const WORD_FILE_NAME: &str = "words.txt";
+
+pub fn ngram_file_name(n: usize) -> String {
+    match n {
+        1 => "monograms.txt".to_string(),
+        2 => "bigrams.txt".to_string(),
+        3 => "trigrams.txt".to_string(),
+        4 => "quadgrams.txt".to_string(),
+        5 => "pentagrams.txt".to_string(),
+        n => format!("{}grams.txt", n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ngram::NgramModel;
+
+    #[test]
+    fn normalize_folds_accents_and_drops_the_rest() {
+        assert_eq!(normalize("Straße, Café & Ñandú 42!"), "STRASSECAFENANDU");
+    }
+
+    #[test]
+    fn counts_do_not_span_files() {
+        let dir = std::env::temp_dir().join(format!("kryptos-corpus-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("a.txt"), "The end").unwrap();
+        fs::write(dir.join("nested").join("b.txt"), "the hen").unwrap();
+
+        let counts = count_corpus(&dir, 2).unwrap();
+        assert_eq!(counts.files, 2);
+        assert_eq!(counts.letters, 12);
+        assert_eq!(counts.ngrams[0]["E"], 4);
+        assert_eq!(counts.ngrams[1]["HE"], 3);
+        assert_eq!(counts.ngrams[1].get("DT"), None);
+        assert_eq!(counts.words["THE"], 2);
+        assert_eq!(corpus_letters(&dir).unwrap(), "THEENDTHEHEN");
+
+        let path = dir.join(ngram_file_name(2));
+        write_counts(&path, &counts.ngrams[1]).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("HE 3\nEN 2\nTH 2\n"));
+        let model = NgramModel::load(&path).unwrap();
+        assert_eq!(model.n(), 2);
+        assert!(model.log_probability(b"HE") > model.log_probability(b"TH"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,190 @@
+// crib_drag.rs
+
+use crate::substitution::{CipherFamily, Tableau};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone)]
+pub struct DragResult {
+    pub offset: usize,
+    pub family: CipherFamily,
+    pub key_fragment: String,
+    pub period: Option<usize>,
+    pub dictionary_word: Option<String>,
+}
+
+impl DragResult {
+    pub fn is_flagged(&self) -> bool {
+        self.period.is_some() || self.dictionary_word.is_some()
+    }
+}
+
+pub struct Dictionary {
+    words: Vec<String>,
+    lookup: HashSet<String>,
+    min_word_length: usize,
+}
+
+impl Dictionary {
+    pub fn new<I, S>(words: I, min_word_length: usize) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let words: Vec<String> = words
+            .into_iter()
+            .map(|w| w.as_ref().trim().to_ascii_uppercase())
+            .filter(|w| w.len() >= min_word_length && w.chars().all(|c| c.is_ascii_uppercase()))
+            .collect();
+        let lookup = words.iter().cloned().collect();
+
+        Self {
+            words,
+            lookup,
+            min_word_length,
+        }
+    }
+
+    // The longest word spelled inside the fragment, or failing that a word
+    // the whole fragment could be a piece of.
+    pub fn find(&self, fragment: &str) -> Option<String> {
+        let n = fragment.len();
+        for len in (self.min_word_length..=n).rev() {
+            for start in 0..=n - len {
+                let piece = &fragment[start..start + len];
+                if self.lookup.contains(piece) {
+                    return Some(piece.to_string());
+                }
+            }
+        }
+
+        if n >= self.min_word_length {
+            return self.words.iter().find(|w| w.contains(fragment)).cloned();
+        }
+        None
+    }
+}
+
+// The key letters the crib implies when laid under the ciphertext at the
+// given offset, or None when the crib runs off the end.
+pub fn implied_key(
+    cipher_chars: &[char],
+    crib: &str,
+    offset: usize,
+    family: CipherFamily,
+    tableau: &Tableau,
+) -> Option<String> {
+    if offset + crib.len() > cipher_chars.len() {
+        return None;
+    }
+
+    crib.chars()
+        .zip(&cipher_chars[offset..])
+        .map(|(p, &c)| {
+            let shift = family.key_shift(tableau.plain_index(p)?, tableau.cipher_index(c)?, 26);
+            Some(tableau.key_letter(shift))
+        })
+        .collect()
+}
+
+// The smallest period the fragment repeats with, requiring at least one full
+// repetition so that short fragments are not flagged trivially.
+pub fn minimal_period(fragment: &str) -> Option<usize> {
+    let chars: Vec<char> = fragment.chars().collect();
+    (1..=chars.len() / 2).find(|&p| (p..chars.len()).all(|i| chars[i] == chars[i - p]))
+}
+
+// Slides the crib over every offset under each family and records the
+// implied key fragment together with any periodicity or dictionary hit.
+pub fn drag_crib(
+    ciphertext: &str,
+    crib: &str,
+    families: &[CipherFamily],
+    tableau: &Tableau,
+    dictionary: &Dictionary,
+) -> Vec<DragResult> {
+    let cipher_chars: Vec<char> = ciphertext.chars().collect();
+    let crib = crib.to_ascii_uppercase();
+    let mut results = Vec::new();
+
+    for offset in 0..cipher_chars.len() {
+        for &family in families {
+            let Some(key_fragment) = implied_key(&cipher_chars, &crib, offset, family, tableau)
+            else {
+                continue;
+            };
+
+            results.push(DragResult {
+                offset,
+                family,
+                period: minimal_period(&key_fragment),
+                dictionary_word: dictionary.find(&key_fragment),
+                key_fragment,
+            });
+        }
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const K1: &str = "EMUFPHZLRFAXYUSDJKZLDKRNSHGNFIVJYQTQUXQBQVYUVLLTREVJYQTMKYRDMFD";
+
+    #[test]
+    fn minimal_period_needs_a_full_repetition() {
+        assert_eq!(minimal_period("PALIMPSESTPALIMPSEST"), Some(10));
+        assert_eq!(minimal_period("ABCABCA"), Some(3));
+        assert_eq!(minimal_period("AAAA"), Some(1));
+        // ABCAB would repeat with period 3, but never completes a second copy.
+        assert_eq!(minimal_period("ABCAB"), None);
+        assert_eq!(minimal_period("PALIMPS"), None);
+        assert_eq!(minimal_period("A"), None);
+        assert_eq!(minimal_period(""), None);
+    }
+
+    #[test]
+    fn dictionary_prefers_the_longest_word_inside_the_fragment() {
+        let dictionary = Dictionary::new(["palimpsest", "limp", "lim", "x1"], 3);
+        assert_eq!(dictionary.find("QLIMPQ"), Some("LIMP".to_string()));
+        // A fragment that is only a piece of a word still finds it.
+        assert_eq!(dictionary.find("MPSES"), Some("PALIMPSEST".to_string()));
+        assert_eq!(dictionary.find("QQ"), None);
+        assert_eq!(dictionary.find("QQQQ"), None);
+    }
+
+    #[test]
+    fn dragging_the_k1_crib_flags_the_true_offset() {
+        let tableau = Tableau::quagmire3("KRYPTOS");
+        let dictionary = Dictionary::new(["PALIMPSEST"], 5);
+        let crib = "betweensubtleshading";
+        let results = drag_crib(K1, crib, &[CipherFamily::Vigenere], &tableau, &dictionary);
+        assert_eq!(results.len(), K1.len() - crib.len() + 1);
+
+        let true_offset = &results[0];
+        assert_eq!(true_offset.key_fragment, "PALIMPSESTPALIMPSEST");
+        assert_eq!(true_offset.period, Some(10));
+        assert_eq!(true_offset.dictionary_word.as_deref(), Some("PALIMPSEST"));
+        assert!(results[1..].iter().all(|r| r.period.is_none()));
+    }
+
+    #[test]
+    fn implied_key_stops_at_the_end_of_the_ciphertext() {
+        let cipher_chars: Vec<char> = "ABC".chars().collect();
+        let tableau = Tableau::straight();
+        let vigenere = CipherFamily::Vigenere;
+        assert_eq!(
+            implied_key(&cipher_chars, "AB", 1, vigenere, &tableau),
+            Some("BB".to_string())
+        );
+        assert_eq!(
+            implied_key(&cipher_chars, "AB", 2, vigenere, &tableau),
+            None
+        );
+        assert_eq!(
+            implied_key(&cipher_chars, "A?", 0, vigenere, &tableau),
+            None
+        );
+    }
+}
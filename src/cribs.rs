@@ -0,0 +1,47 @@
+// cribs.rs
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crib {
+    pub position: usize,
+    pub text: String,
+}
+
+impl Crib {
+    pub fn new(position: usize, text: &str) -> Self {
+        Self {
+            position,
+            text: text.to_ascii_uppercase(),
+        }
+    }
+
+    pub fn end(&self) -> usize {
+        self.position + self.text.len()
+    }
+
+    // Iterates over (position, letter) pairs covered by the crib.
+    pub fn letters(&self) -> impl Iterator<Item = (usize, char)> + '_ {
+        self.text
+            .chars()
+            .enumerate()
+            .map(move |(i, c)| (self.position + i, c))
+    }
+}
+
+// The published K4 plaintext fragments at their zero-based positions.
+pub fn k4_cribs() -> Vec<Crib> {
+    vec![Crib::new(21, "EASTNORTHEAST"), Crib::new(63, "BERLINCLOCK")]
+}
+
+// Maps each covered position to its required letter, or None where the
+// cribs leave the plaintext unconstrained.
+pub fn crib_mask(cribs: &[Crib], length: usize) -> Vec<Option<char>> {
+    let mut mask = vec![None; length];
+    for crib in cribs {
+        for (position, c) in crib.letters() {
+            if position < length {
+                mask[position] = Some(c);
+            }
+        }
+    }
+    mask
+}
@@ -1,7 +1,11 @@
+use crate::language::LanguageFit;
+use crate::preprocessing::RemovalPattern;
+use crate::scorer::ScoreBreakdown;
+use crate::significance::Significance;
 use std::cmp::{Ordering, Reverse};
 use std::collections::BinaryHeap;
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Candidate {
     pub score: f64,
     pub plaintext: String,
@@ -10,6 +14,10 @@ pub struct Candidate {
     pub alphabet_index: usize,
     pub keyword: String,
     pub alphabet: String, // Added this field
+    pub removal: Option<RemovalPattern>,
+    pub breakdown: Option<ScoreBreakdown>,
+    pub significance: Option<Significance>,
+    pub language: Option<LanguageFit>,
 }
 
 impl Candidate {
@@ -30,6 +38,10 @@ impl Candidate {
             alphabet_index,
             keyword,
             alphabet, // Added this field
+            removal: None,
+            breakdown: None,
+            significance: None,
+            language: None,
         }
     }
 }
@@ -38,7 +50,21 @@ impl Eq for Candidate {}
 
 impl Ord for Candidate {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap_or(Ordering::Equal)
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| self.plaintext.cmp(&other.plaintext))
+            .then_with(|| self.transposition.cmp(&other.transposition))
+            .then_with(|| self.substitution.cmp(&other.substitution))
+            .then_with(|| self.alphabet_index.cmp(&other.alphabet_index))
+            .then_with(|| self.keyword.cmp(&other.keyword))
+            .then_with(|| self.alphabet.cmp(&other.alphabet))
+            .then_with(|| self.removal.cmp(&other.removal))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
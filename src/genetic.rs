@@ -0,0 +1,202 @@
+// genetic.rs
+
+use crate::layers::{LayerBounds, LayerStack};
+use crate::scorer::Scorer;
+use rand::Rng;
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone)]
+pub struct GeneticSearch {
+    pub population: usize,
+    pub generations: usize,
+    // Chance that a child is bred from two parents rather than copied from one.
+    pub crossover_rate: f64,
+    // Chance that a child is mutated after breeding.
+    pub mutation_rate: f64,
+    // The best individuals carried over unchanged each generation.
+    pub elites: usize,
+    // Individuals drawn per tournament when choosing a parent.
+    pub tournament: usize,
+}
+
+impl Default for GeneticSearch {
+    fn default() -> Self {
+        Self {
+            population: 200,
+            generations: 300,
+            crossover_rate: 0.7,
+            mutation_rate: 0.5,
+            elites: 4,
+            tournament: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Individual {
+    pub stack: LayerStack,
+    pub plaintext: String,
+    pub score: f64,
+}
+
+impl Individual {
+    pub fn evaluate(stack: LayerStack, ciphertext: &str, scorer: &dyn Scorer) -> Self {
+        let plaintext = stack.decrypt(ciphertext);
+        let score = scorer.score(&plaintext);
+        Self {
+            stack,
+            plaintext,
+            score,
+        }
+    }
+}
+
+// Evolves layer stacks within the bounds, scoring each decryption with the
+// scorer. Breeding draws from `rng` on the calling thread and only the
+// scoring runs in parallel. `on_generation` sees the best individual after
+// each generation. Returns the final population, best first.
+pub fn evolve<R, F>(
+    ciphertext: &str,
+    bounds: &LayerBounds,
+    config: &GeneticSearch,
+    scorer: &dyn Scorer,
+    rng: &mut R,
+    mut on_generation: F,
+) -> Vec<Individual>
+where
+    R: Rng,
+    F: FnMut(usize, &Individual),
+{
+    let size = config.population.max(2);
+    let stacks: Vec<LayerStack> = (0..size).map(|_| bounds.random_stack(rng)).collect();
+    let mut population = evaluate_all(stacks, ciphertext, scorer);
+
+    for generation in 0..config.generations {
+        let mut next: Vec<LayerStack> = population
+            .iter()
+            .take(config.elites.min(size))
+            .map(|individual| individual.stack.clone())
+            .collect();
+
+        // Copies of a stack already in the next generation are mutated
+        // again, and replaced by a random stack if that keeps failing, so
+        // the elites cannot crowd out the rest of the population.
+        let mut seen: HashSet<LayerStack> = next.iter().cloned().collect();
+        while next.len() < size {
+            let parent = tournament(&population, config.tournament, rng);
+            let mut child = if rng.gen_bool(config.crossover_rate.clamp(0.0, 1.0)) {
+                let other = tournament(&population, config.tournament, rng);
+                bounds.crossover(&parent.stack, &other.stack, rng)
+            } else {
+                parent.stack.clone()
+            };
+            if rng.gen_bool(config.mutation_rate.clamp(0.0, 1.0)) {
+                bounds.mutate_stack(&mut child, rng);
+            }
+            for _ in 0..4 {
+                if !seen.contains(&child) {
+                    break;
+                }
+                bounds.mutate_stack(&mut child, rng);
+            }
+            if seen.contains(&child) {
+                child = bounds.random_stack(rng);
+            }
+            seen.insert(child.clone());
+            next.push(child);
+        }
+
+        population = evaluate_all(next, ciphertext, scorer);
+        on_generation(generation, &population[0]);
+    }
+
+    population
+}
+
+// The population without repeated stacks, best first.
+pub fn distinct(population: &[Individual]) -> Vec<&Individual> {
+    let mut seen = HashSet::new();
+    population
+        .iter()
+        .filter(|individual| seen.insert(&individual.stack))
+        .collect()
+}
+
+fn evaluate_all(stacks: Vec<LayerStack>, ciphertext: &str, scorer: &dyn Scorer) -> Vec<Individual> {
+    let mut population: Vec<Individual> = stacks
+        .into_par_iter()
+        .map(|stack| Individual::evaluate(stack, ciphertext, scorer))
+        .collect();
+    population.sort_by(|a, b| b.score.total_cmp(&a.score));
+    population
+}
+
+fn tournament<'a, R: Rng>(
+    population: &'a [Individual],
+    size: usize,
+    rng: &mut R,
+) -> &'a Individual {
+    (0..size.max(1))
+        .map(|_| &population[rng.gen_range(0..population.len())])
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corpus;
+    use crate::layers::{CipherLayer, LayerKind};
+    use crate::scorer::MonogramScorer;
+    use crate::seeding;
+    use crate::substitution::{self, CipherFamily, Tableau, STRAIGHT_ALPHABET};
+
+    #[test]
+    fn evolve_recovers_a_planted_periodic_layer() {
+        let plaintext = &corpus::normalize(corpus::KRYPTOS_PLAINTEXT)[..300];
+        let ciphertext = substitution::periodic_encrypt(
+            plaintext,
+            "ORB",
+            CipherFamily::Vigenere,
+            &Tableau::straight(),
+        );
+        let bounds = LayerBounds {
+            kinds: vec![LayerKind::Periodic],
+            max_layers: 1,
+            max_width: 2,
+            max_key_length: 4,
+            families: vec![CipherFamily::Vigenere],
+            alphabets: vec![STRAIGHT_ALPHABET.to_string()],
+        };
+        let config = GeneticSearch {
+            population: 60,
+            generations: 80,
+            ..GeneticSearch::default()
+        };
+        let mut rng = seeding::rng(5);
+        let mut best_scores = Vec::new();
+        let population = evolve(
+            &ciphertext,
+            &bounds,
+            &config,
+            &MonogramScorer::english(),
+            &mut rng,
+            |_, best| best_scores.push(best.score),
+        );
+
+        assert_eq!(population.len(), 60);
+        assert_eq!(best_scores.len(), 80);
+        assert!(best_scores.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(population[0].plaintext, plaintext);
+        assert_eq!(
+            population[0].stack.layers,
+            [CipherLayer::Periodic {
+                family: CipherFamily::Vigenere,
+                alphabet: STRAIGHT_ALPHABET.to_string(),
+                key: "ORB".to_string(),
+            }]
+        );
+        assert_eq!(distinct(&population).len(), population.len());
+    }
+}
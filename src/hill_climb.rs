@@ -0,0 +1,291 @@
+// hill_climb.rs
+
+use crate::scorer::Scorer;
+use crate::seeding;
+use crate::substitution::{self, CipherFamily, Tableau, STRAIGHT_ALPHABET};
+use rand::Rng;
+use rayon::prelude::*;
+
+// The keys a search may propose: strings of `length` letters from the
+// alphabet, with every letter at most once when `distinct` is set (keyed
+// alphabets and monoalphabetic keys).
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeySpace {
+    pub alphabet: Vec<char>,
+    pub length: usize,
+    pub distinct: bool,
+}
+
+impl KeySpace {
+    pub fn periodic(length: usize) -> Self {
+        Self {
+            alphabet: STRAIGHT_ALPHABET.chars().collect(),
+            length: length.max(1),
+            distinct: false,
+        }
+    }
+
+    pub fn alphabet_permutation() -> Self {
+        Self {
+            alphabet: STRAIGHT_ALPHABET.chars().collect(),
+            length: 26,
+            distinct: true,
+        }
+    }
+
+    pub fn random_key<R: Rng>(&self, rng: &mut R) -> Vec<char> {
+        if self.distinct {
+            let mut letters = self.alphabet.clone();
+            for i in (1..letters.len()).rev() {
+                letters.swap(i, rng.gen_range(0..=i));
+            }
+            letters.truncate(self.length);
+            letters
+        } else {
+            (0..self.length)
+                .map(|_| self.alphabet[rng.gen_range(0..self.alphabet.len())])
+                .collect()
+        }
+    }
+
+    // Applies one random move in place. Returns false when the move cannot
+    // change this key, e.g. a swap in a one-letter key.
+    pub fn mutate<R: Rng>(&self, key: &mut [char], mv: KeyMove, rng: &mut R) -> bool {
+        let n = key.len();
+        if n == 0 {
+            return false;
+        }
+        match mv {
+            KeyMove::ChangeLetter => {
+                let i = rng.gen_range(0..n);
+                let letter = self.alphabet[rng.gen_range(0..self.alphabet.len())];
+                if letter == key[i] {
+                    return false;
+                }
+                // A distinct key gives the letter up from wherever it was,
+                // or takes it from outside the key when it is shorter than
+                // the alphabet.
+                if self.distinct {
+                    if let Some(j) = key.iter().position(|&c| c == letter) {
+                        key.swap(i, j);
+                        return true;
+                    }
+                }
+                key[i] = letter;
+                true
+            }
+            KeyMove::Swap => {
+                let i = rng.gen_range(0..n);
+                let j = rng.gen_range(0..n);
+                if i == j || key[i] == key[j] {
+                    return false;
+                }
+                key.swap(i, j);
+                true
+            }
+            KeyMove::Rotate => {
+                if n < 2 {
+                    return false;
+                }
+                key.rotate_left(rng.gen_range(1..n));
+                true
+            }
+        }
+    }
+}
+
+// Changing one key letter, exchanging two, or rotating the whole key, which
+// realigns a periodic key that was found with the right letters at the
+// wrong phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyMove {
+    ChangeLetter,
+    Swap,
+    Rotate,
+}
+
+impl KeyMove {
+    pub const ALL: [KeyMove; 3] = [KeyMove::ChangeLetter, KeyMove::Swap, KeyMove::Rotate];
+}
+
+// The keyed ciphers the CLI can search.
+#[derive(Debug, Clone)]
+pub enum KeyedCipher {
+    Periodic {
+        family: CipherFamily,
+        tableau: Box<Tableau>,
+    },
+    // The key lists the plaintext letter for each ciphertext letter A-Z.
+    Monoalphabetic,
+}
+
+impl KeyedCipher {
+    pub fn key_space(&self, key_length: usize) -> KeySpace {
+        match self {
+            KeyedCipher::Periodic { .. } => KeySpace::periodic(key_length),
+            KeyedCipher::Monoalphabetic => KeySpace::alphabet_permutation(),
+        }
+    }
+
+    pub fn decrypt(&self, ciphertext: &str, key: &str) -> String {
+        match self {
+            KeyedCipher::Periodic { family, tableau } => {
+                substitution::periodic_decrypt(ciphertext, key, *family, tableau)
+            }
+            KeyedCipher::Monoalphabetic => {
+                substitution::monoalphabetic_substitution(ciphertext, key, STRAIGHT_ALPHABET)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HillClimbing {
+    // Proposals per restart.
+    pub iterations: usize,
+    // A restart gives up after this many proposals in a row fail to improve.
+    pub patience: usize,
+    pub restarts: usize,
+    // Each restart draws from its own stream of this seed.
+    pub seed: u64,
+}
+
+impl Default for HillClimbing {
+    fn default() -> Self {
+        Self {
+            iterations: 20_000,
+            patience: 2_000,
+            restarts: 16,
+            seed: seeding::random_seed(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClimbResult {
+    pub key: String,
+    pub plaintext: String,
+    pub score: f64,
+}
+
+// Climbs from a random key once per restart, in parallel, keeping only
+// strict improvements. Returns each restart's summit, best first.
+pub fn hill_climb<F>(
+    space: &KeySpace,
+    config: &HillClimbing,
+    decrypt: F,
+    scorer: &dyn Scorer,
+) -> Vec<ClimbResult>
+where
+    F: Fn(&str) -> String + Sync,
+{
+    let mut results: Vec<ClimbResult> = (0..config.restarts.max(1))
+        .into_par_iter()
+        .map(|restart| {
+            let mut rng = seeding::stream(config.seed, restart);
+            climb(space, config, &decrypt, scorer, &mut rng)
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results
+}
+
+fn climb<F, R>(
+    space: &KeySpace,
+    config: &HillClimbing,
+    decrypt: &F,
+    scorer: &dyn Scorer,
+    rng: &mut R,
+) -> ClimbResult
+where
+    F: Fn(&str) -> String,
+    R: Rng,
+{
+    let mut key = space.random_key(rng);
+    let mut key_string: String = key.iter().collect();
+    let mut plaintext = decrypt(&key_string);
+    let mut score = scorer.score(&plaintext);
+
+    let mut stalled = 0;
+    for _ in 0..config.iterations {
+        if stalled >= config.patience {
+            break;
+        }
+        let mut proposal = key.clone();
+        let mv = KeyMove::ALL[rng.gen_range(0..KeyMove::ALL.len())];
+        if !space.mutate(&mut proposal, mv, rng) {
+            continue;
+        }
+
+        let proposal_string: String = proposal.iter().collect();
+        let proposal_plaintext = decrypt(&proposal_string);
+        let proposal_score = scorer.score(&proposal_plaintext);
+        if proposal_score > score {
+            key = proposal;
+            key_string = proposal_string;
+            plaintext = proposal_plaintext;
+            score = proposal_score;
+            stalled = 0;
+        } else {
+            stalled += 1;
+        }
+    }
+
+    ClimbResult {
+        key: key_string,
+        plaintext,
+        score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corpus;
+    use crate::scorer::MonogramScorer;
+
+    #[test]
+    fn hill_climb_recovers_a_planted_periodic_key() {
+        let plaintext = &corpus::normalize(corpus::KRYPTOS_PLAINTEXT)[..300];
+        let cipher = KeyedCipher::Periodic {
+            family: CipherFamily::Vigenere,
+            tableau: Box::new(Tableau::straight()),
+        };
+        let ciphertext = substitution::periodic_encrypt(
+            plaintext,
+            "LAYER",
+            CipherFamily::Vigenere,
+            &Tableau::straight(),
+        );
+        let config = HillClimbing {
+            iterations: 5_000,
+            patience: 1_000,
+            restarts: 4,
+            seed: 11,
+        };
+        let results = hill_climb(
+            &cipher.key_space(5),
+            &config,
+            |key| cipher.decrypt(&ciphertext, key),
+            &MonogramScorer::english(),
+        );
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].key, "LAYER");
+        assert_eq!(results[0].plaintext, plaintext);
+    }
+
+    #[test]
+    fn distinct_keys_stay_permutations() {
+        let space = KeySpace::alphabet_permutation();
+        let mut rng = seeding::rng(2);
+        let mut key = space.random_key(&mut rng);
+        for _ in 0..200 {
+            for mv in KeyMove::ALL {
+                space.mutate(&mut key, mv, &mut rng);
+            }
+        }
+        let mut sorted = key.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, space.alphabet);
+    }
+}
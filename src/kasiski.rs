@@ -0,0 +1,203 @@
+// kasiski.rs
+
+use crate::statistics;
+use std::collections::HashMap;
+
+// A ciphertext substring that occurs more than once. Only maximal repeats
+// are kept: a repeat whose every occurrence extends to a longer repeat is
+// reported as the longer one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repeat {
+    pub sequence: String,
+    pub positions: Vec<usize>,
+    // Distances between every pair of occurrences.
+    pub spacings: Vec<usize>,
+    pub gcd: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeriodEvidence {
+    pub period: usize,
+    // Spacings divisible by the period.
+    pub spacings: usize,
+    // Spacings divisible by the period, each weighted by the letters in its
+    // repeat beyond the minimum, since long repeats are rarely chance.
+    pub weight: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct KasiskiReport {
+    pub repeats: Vec<Repeat>,
+    // Ranked most likely first.
+    pub periods: Vec<PeriodEvidence>,
+}
+
+pub fn find_repeats(text: &str, min_length: usize) -> Vec<Repeat> {
+    let letters = statistics::letters(text);
+    let min_length = min_length.max(1);
+    let mut repeats = Vec::new();
+
+    // Occurrences may overlap, so a repeat can be one letter shorter than
+    // the text.
+    let mut length = min_length;
+    while length < letters.len() {
+        let mut occurrences: HashMap<&[u8], Vec<usize>> = HashMap::new();
+        for (position, window) in letters.windows(length).enumerate() {
+            occurrences.entry(window).or_default().push(position);
+        }
+        let mut found = false;
+        for (window, positions) in occurrences {
+            if positions.len() < 2 {
+                continue;
+            }
+            found = true;
+            if !is_maximal(&letters, length, &positions) {
+                continue;
+            }
+            let spacings: Vec<usize> = positions
+                .iter()
+                .enumerate()
+                .flat_map(|(i, a)| positions[i + 1..].iter().map(move |b| b - a))
+                .collect();
+            let gcd = spacings.iter().fold(0, |g, &s| gcd(g, s));
+            repeats.push(Repeat {
+                sequence: String::from_utf8_lossy(window).into_owned(),
+                positions,
+                spacings,
+                gcd,
+            });
+        }
+        // No repeat of this length means none of any longer length.
+        if !found {
+            break;
+        }
+        length += 1;
+    }
+
+    repeats.sort_by(|a, b| {
+        b.sequence
+            .len()
+            .cmp(&a.sequence.len())
+            .then_with(|| a.positions.cmp(&b.positions))
+    });
+    repeats
+}
+
+// A repeat is not maximal when the letter just before, or just after, is
+// the same at every occurrence.
+fn is_maximal(letters: &[u8], length: usize, positions: &[usize]) -> bool {
+    let same_neighbour = |neighbour: &dyn Fn(usize) -> Option<u8>| {
+        let first = neighbour(positions[0]);
+        first.is_some() && positions.iter().all(|&p| neighbour(p) == first)
+    };
+    let before = |p: usize| p.checked_sub(1).map(|i| letters[i]);
+    let after = |p: usize| letters.get(p + length).copied();
+    !same_neighbour(&before) && !same_neighbour(&after)
+}
+
+pub fn period_evidence(
+    repeats: &[Repeat],
+    min_length: usize,
+    max_period: usize,
+) -> Vec<PeriodEvidence> {
+    let mut periods: Vec<PeriodEvidence> = (2..=max_period)
+        .map(|period| {
+            let mut evidence = PeriodEvidence {
+                period,
+                spacings: 0,
+                weight: 0,
+            };
+            for repeat in repeats {
+                let extra = repeat.sequence.len() + 1 - min_length.min(repeat.sequence.len());
+                for spacing in &repeat.spacings {
+                    if spacing % period == 0 {
+                        evidence.spacings += 1;
+                        evidence.weight += extra;
+                    }
+                }
+            }
+            evidence
+        })
+        .filter(|evidence| evidence.spacings > 0)
+        .collect();
+
+    periods.sort_by(|a, b| {
+        b.weight
+            .cmp(&a.weight)
+            .then_with(|| b.period.cmp(&a.period))
+    });
+    periods
+}
+
+pub fn kasiski(text: &str, min_length: usize, max_period: usize) -> KasiskiReport {
+    let repeats = find_repeats(text, min_length);
+    let periods = period_evidence(&repeats, min_length, max_period);
+    KasiskiReport { repeats, periods }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_repeats_are_reported_at_their_longest() {
+        let repeats = find_repeats("ABC ABC ABC", 3);
+        // BCA, CAB and every other repeat inside ABCABC always extend to it;
+        // ABC is kept because its third occurrence does not.
+        assert_eq!(
+            repeats,
+            vec![
+                Repeat {
+                    sequence: "ABCABC".to_string(),
+                    positions: vec![0, 3],
+                    spacings: vec![3],
+                    gcd: 3,
+                },
+                Repeat {
+                    sequence: "ABC".to_string(),
+                    positions: vec![0, 3, 6],
+                    spacings: vec![3, 6, 3],
+                    gcd: 3,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn a_run_of_one_letter_repeats_with_spacing_one() {
+        // Each shorter run has an occurrence the longer one does not cover.
+        let repeats: Vec<(String, Vec<usize>, usize)> = find_repeats("AAAAA", 2)
+            .into_iter()
+            .map(|r| (r.sequence, r.positions, r.gcd))
+            .collect();
+        assert_eq!(
+            repeats,
+            vec![
+                ("AAAA".to_string(), vec![0, 1], 1),
+                ("AAA".to_string(), vec![0, 1, 2], 1),
+                ("AA".to_string(), vec![0, 1, 2, 3], 1),
+            ]
+        );
+        assert!(find_repeats("ABCDEF", 2).is_empty());
+    }
+
+    #[test]
+    fn periods_are_ranked_by_weighted_spacings() {
+        let report = kasiski("ABCABCABC", 3, 6);
+        // ABCABC counts 6 + 1 - 3 = 4 per spacing, ABC counts 1.
+        let periods: Vec<(usize, usize, usize)> = report
+            .periods
+            .iter()
+            .map(|e| (e.period, e.spacings, e.weight))
+            .collect();
+        assert_eq!(periods, vec![(3, 4, 7), (6, 1, 1), (2, 1, 1)]);
+    }
+}
@@ -2,21 +2,20 @@
 
 use rand::Rng;
 
-pub fn grid_key_generator() -> String {
+pub fn grid_key_generator<R: Rng>(rng: &mut R) -> String {
     const GRID_SIZE: usize = 5;
-    let mut rng = rand::thread_rng();
     let mut grid = [[0; GRID_SIZE]; GRID_SIZE];
 
-    for i in 0..GRID_SIZE {
-        for j in 0..GRID_SIZE {
-            grid[i][j] = rng.gen_range(0..31);
+    for row in grid.iter_mut() {
+        for cell in row.iter_mut() {
+            *cell = rng.gen_range(0..31);
         }
     }
 
     let mut key = String::new();
-    for i in 0..GRID_SIZE {
-        for j in 0..GRID_SIZE {
-            key.push((grid[i][j] + b'A') as char);
+    for row in &grid {
+        for &cell in row {
+            key.push((cell + b'A') as char);
         }
     }
     key
@@ -49,6 +48,11 @@ pub fn generate_alphabets(n: usize, length: Option<usize>) -> Vec<String> {
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 
+// The keyword a wordlist line gives for keywords of `length` letters.
+pub fn wordlist_keyword(line: &str, length: usize) -> Option<String> {
+    (line.len() == length).then(|| line.to_ascii_uppercase())
+}
+
 pub fn generate_keywords_from_wordlist(wordlist_file: &str, length: usize) -> Vec<String> {
     let file = File::open(wordlist_file).expect("Unable to open wordlist file");
     let mut reader = BufReader::new(file);
@@ -66,14 +70,12 @@ pub fn generate_keywords_from_wordlist(wordlist_file: &str, length: usize) -> Ve
         .expect("Failed to seek to the beginning of the file");
 
     let mut processed_words = 0;
-    let progress_interval = total_words / 100; // Print progress every 1% of total words
+    let progress_interval = (total_words / 100).max(1); // Print progress every 1% of total words
 
-    for line in reader.lines().filter_map(|line| line.ok()) {
+    for line in reader.lines().map_while(Result::ok) {
         processed_words += 1;
 
-        if line.len() == length {
-            keywords.push(line.to_ascii_uppercase());
-        }
+        keywords.extend(wordlist_keyword(&line, length));
 
         if processed_words % progress_interval == 0 {
             let progress = processed_words as f64 / total_words as f64 * 100.0;
@@ -102,19 +104,30 @@ pub fn decimate_alphabet(alphabet: &str, keyword: &str, ciphertext_length: usize
     decimated_alphabet
 }
 
-pub fn generate_keywords(n: usize, length: usize) -> Vec<String> {
+pub fn generate_keywords<R: Rng>(n: usize, length: usize, rng: &mut R) -> Vec<String> {
     let alphabet = "NGHIJLMNQUVWXZKRYPTOSABCDEFGHIJL";
 
     (0..n)
         .map(|_| {
             (0..length)
-                .map(|_| {
-                    alphabet
-                        .chars()
-                        .nth(rand::thread_rng().gen_range(0..31))
-                        .unwrap()
-                })
+                .map(|_| alphabet.chars().nth(rng.gen_range(0..31)).unwrap())
                 .collect()
         })
         .collect()
 }
+
+// A keyword-mixed alphabet: the keyword's distinct letters followed by the
+// rest of A-Z in order, e.g. KRYPTOS -> KRYPTOSABCDEFGHIJLMNQUVWXZ.
+pub fn keyed_alphabet(keyword: &str) -> String {
+    let mut alphabet = String::with_capacity(26);
+    for c in keyword
+        .chars()
+        .map(|c| c.to_ascii_uppercase())
+        .chain('A'..='Z')
+    {
+        if c.is_ascii_uppercase() && !alphabet.contains(c) {
+            alphabet.push(c);
+        }
+    }
+    alphabet
+}
@@ -0,0 +1,227 @@
+// known_plaintext.rs
+
+use crate::cribs::Crib;
+use crate::substitution::{CipherFamily, Tableau};
+
+// The key shift implied at each crib position under one family and tableau.
+pub fn derive_keystream(
+    ciphertext: &str,
+    cribs: &[Crib],
+    family: CipherFamily,
+    tableau: &Tableau,
+) -> Vec<(usize, usize)> {
+    let cipher_chars: Vec<char> = ciphertext.chars().collect();
+    let mut keystream = Vec::new();
+
+    for crib in cribs {
+        for (position, p) in crib.letters() {
+            let Some(&c) = cipher_chars.get(position) else {
+                continue;
+            };
+            if let (Some(p), Some(c)) = (tableau.plain_index(p), tableau.cipher_index(c)) {
+                keystream.push((position, family.key_shift(p, c, 26)));
+            }
+        }
+    }
+
+    keystream
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeriodSolution {
+    pub period: usize,
+    pub key: Vec<Option<usize>>,
+    // How many crib letters shared a key slot with an earlier one and agreed
+    // with it. Zero means the period was never actually tested.
+    pub agreements: usize,
+}
+
+impl PeriodSolution {
+    pub fn key_string(&self, tableau: &Tableau) -> String {
+        self.key
+            .iter()
+            .map(|shift| shift.map_or('?', |k| tableau.key_letter(k)))
+            .collect()
+    }
+}
+
+// Folds the keystream onto each period and keeps the periods where every
+// key slot receives a single shift.
+pub fn consistent_periods(keystream: &[(usize, usize)], max_period: usize) -> Vec<PeriodSolution> {
+    (1..=max_period)
+        .filter_map(|period| {
+            let mut key = vec![None; period];
+            let mut agreements = 0;
+
+            for &(position, shift) in keystream {
+                match key[position % period] {
+                    Some(existing) if existing != shift => return None,
+                    Some(_) => agreements += 1,
+                    None => key[position % period] = Some(shift),
+                }
+            }
+
+            Some(PeriodSolution {
+                period,
+                key,
+                agreements,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyRecovery {
+    pub family: CipherFamily,
+    pub tableau: Tableau,
+    pub keystream: Vec<(usize, usize)>,
+    pub solutions: Vec<PeriodSolution>,
+}
+
+impl KeyRecovery {
+    // The derived key letters laid out under each crib, for display.
+    pub fn keystream_string(&self) -> String {
+        self.keystream
+            .iter()
+            .map(|&(_, shift)| self.tableau.key_letter(shift))
+            .collect()
+    }
+}
+
+// Places the cribs under every family and tableau and reports those for
+// which at least one tested period up to max_period is consistent.
+pub fn recover_keys(
+    ciphertext: &str,
+    cribs: &[Crib],
+    tableaux: &[Tableau],
+    max_period: usize,
+) -> Vec<KeyRecovery> {
+    let mut recoveries = Vec::new();
+
+    for tableau in tableaux {
+        for family in CipherFamily::ALL {
+            let keystream = derive_keystream(ciphertext, cribs, family, tableau);
+            let solutions: Vec<PeriodSolution> = consistent_periods(&keystream, max_period)
+                .into_iter()
+                .filter(|solution| solution.agreements > 0)
+                .collect();
+
+            if !solutions.is_empty() {
+                recoveries.push(KeyRecovery {
+                    family,
+                    tableau: tableau.clone(),
+                    keystream,
+                    solutions,
+                });
+            }
+        }
+    }
+
+    recoveries
+}
+
+// The straight tableau plus Quagmire I-IV for every keyword (and every
+// ordered keyword pair for Quagmire IV).
+pub fn quagmire_tableaux(keywords: &[String]) -> Vec<Tableau> {
+    let mut tableaux = vec![Tableau::straight()];
+    for keyword in keywords {
+        tableaux.push(Tableau::quagmire1(keyword));
+        tableaux.push(Tableau::quagmire2(keyword));
+        tableaux.push(Tableau::quagmire3(keyword));
+    }
+    for plain_keyword in keywords {
+        for cipher_keyword in keywords {
+            if plain_keyword != cipher_keyword {
+                tableaux.push(Tableau::quagmire4(plain_keyword, cipher_keyword));
+            }
+        }
+    }
+    tableaux
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::substitution::periodic_encrypt;
+
+    const K1: &str = "EMUFPHZLRFAXYUSDJKZLDKRNSHGNFIVJYQTQUXQBQVYUVLLTREVJYQTMKYRDMFD";
+
+    fn k1_recovery(crib: Crib) -> (Vec<(usize, usize)>, Tableau) {
+        let tableau = Tableau::quagmire3("KRYPTOS");
+        let keystream = derive_keystream(K1, &[crib], CipherFamily::Vigenere, &tableau);
+        (keystream, tableau)
+    }
+
+    #[test]
+    fn k1_crib_derives_palimpsest_at_period_ten() {
+        let (keystream, tableau) = k1_recovery(Crib::new(0, "BETWEENSUBTLESHADING"));
+        let letters: String = keystream
+            .iter()
+            .map(|&(_, shift)| tableau.key_letter(shift))
+            .collect();
+        assert_eq!(letters, "PALIMPSESTPALIMPSEST");
+
+        let solutions = consistent_periods(&keystream, 12);
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0].period, 10);
+        assert_eq!(solutions[0].agreements, 10);
+        assert_eq!(solutions[0].key_string(&tableau), "PALIMPSEST");
+    }
+
+    #[test]
+    fn beaufort_key_is_reported_at_its_period_and_multiples() {
+        let tableau = Tableau::straight();
+        let plaintext = "WEAREDISCOVEREDFLEEATONCE";
+        let ciphertext = periodic_encrypt(plaintext, "FORT", CipherFamily::Beaufort, &tableau);
+        let cribs = [Crib::new(0, plaintext)];
+
+        let recoveries = recover_keys(&ciphertext, &cribs, std::slice::from_ref(&tableau), 10);
+        assert_eq!(recoveries.len(), 1);
+        let recovery = &recoveries[0];
+        assert_eq!(recovery.family, CipherFamily::Beaufort);
+        assert_eq!(&recovery.keystream_string()[..8], "FORTFORT");
+        let periods: Vec<usize> = recovery.solutions.iter().map(|s| s.period).collect();
+        assert_eq!(periods, vec![4, 8]);
+        assert_eq!(recovery.solutions[0].key_string(&tableau), "FORT");
+        assert_eq!(recovery.solutions[0].agreements, 21);
+    }
+
+    #[test]
+    fn periods_longer_than_the_crib_are_untested() {
+        let (keystream, _) = k1_recovery(Crib::new(0, "BETW"));
+        let solutions = consistent_periods(&keystream, 12);
+        // PALI repeats no letter, so only periods of 4 or more survive and
+        // none of them ever compares two crib letters.
+        assert_eq!(
+            solutions.iter().map(|s| s.period).collect::<Vec<_>>(),
+            (4..=12).collect::<Vec<_>>()
+        );
+        assert!(solutions.iter().all(|s| s.agreements == 0));
+        assert!(recover_keys(
+            K1,
+            &[Crib::new(0, "BETW")],
+            &[Tableau::quagmire3("KRYPTOS")],
+            12
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn cribs_past_the_ciphertext_are_skipped() {
+        let (keystream, _) = k1_recovery(Crib::new(K1.len() - 2, "ION"));
+        assert_eq!(
+            keystream.iter().map(|&(p, _)| p).collect::<Vec<_>>(),
+            vec![K1.len() - 2, K1.len() - 1]
+        );
+    }
+
+    #[test]
+    fn quagmire_tableaux_cover_every_keyword_and_pair() {
+        let keywords = ["KRYPTOS".to_string(), "ABSCISSA".to_string()];
+        let tableaux = quagmire_tableaux(&keywords);
+        assert_eq!(tableaux.len(), 1 + 3 * 2 + 2);
+        assert!(tableaux
+            .iter()
+            .any(|t| t.name == "quagmire4:ABSCISSA:KRYPTOS"));
+    }
+}
@@ -0,0 +1,296 @@
+// language.rs
+
+use crate::corpus;
+use crate::ngram::{NgramModel, MAX_NGRAM_LENGTH};
+use crate::scoring;
+use crate::segmentation::WordModel;
+use crate::statistics;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+// A plaintext language: letter frequencies and a word model, bundled for a
+// few languages, plus an optional n-gram model loaded from a build-ngrams
+// output directory. The bundled languages carry no n-grams, so on their own
+// they are told apart by letter frequencies only; `load` is needed for
+// n-gram fits.
+#[derive(Debug, Clone)]
+pub struct Language {
+    pub name: String,
+    pub letter_frequencies: [f64; 26],
+    pub word_model: Arc<WordModel>,
+    pub ngram_model: Option<Arc<NgramModel>>,
+}
+
+// How well a text fits one language.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageFit {
+    pub language: String,
+    // Mean log10 probability per n-gram under the language's n-gram model,
+    // or per letter under its letter frequencies when no model is loaded.
+    pub fit: f64,
+    // What the fit was computed from: "monograms" or, say, "4-grams".
+    pub model: String,
+    pub word_coverage: f64,
+}
+
+pub const BUNDLED_LANGUAGES: &[&str] = &["english", "german", "french", "russian", "latin"];
+
+// Percentages A-Z. Accented letters are folded as `corpus::normalize` does,
+// and Russian is counted after transliteration (e.g. Ш -> SH, Я -> YA).
+const GERMAN_FREQUENCIES: [f64; 26] = [
+    6.516, 1.886, 2.732, 5.076, 16.396, 1.656, 3.009, 4.577, 6.550, 0.268, 1.417, 3.437, 2.534,
+    9.776, 2.594, 0.670, 0.018, 7.003, 7.270, 6.154, 4.166, 0.846, 1.921, 0.034, 0.039, 1.134,
+];
+const FRENCH_FREQUENCIES: [f64; 26] = [
+    7.636, 0.901, 3.260, 3.669, 14.715, 1.066, 0.866, 0.737, 7.529, 0.613, 0.074, 5.456, 2.968,
+    7.095, 5.796, 2.521, 1.362, 6.693, 7.948, 7.244, 6.311, 1.838, 0.049, 0.427, 0.128, 0.326,
+];
+const RUSSIAN_FREQUENCIES: [f64; 26] = [
+    10.02, 1.59, 1.80, 2.98, 8.77, 0.26, 1.70, 4.80, 7.35, 0.01, 4.46, 4.40, 3.21, 6.70, 10.97,
+    2.81, 0.01, 4.73, 7.40, 6.74, 3.26, 4.54, 0.01, 0.01, 5.76, 2.59,
+];
+const LATIN_FREQUENCIES: [f64; 26] = [
+    8.89, 1.58, 3.99, 2.77, 11.38, 0.93, 1.21, 0.69, 11.44, 0.01, 0.01, 3.15, 5.38, 6.27, 5.40,
+    3.03, 1.51, 6.67, 7.60, 8.00, 8.46, 0.96, 0.01, 0.60, 0.07, 0.01,
+];
+
+const GERMAN_WORDS: &[&str] = &[
+    "DER", "DIE", "DAS", "UND", "IST", "NICHT", "ICH", "SIE", "ES", "EIN", "EINE", "ZU", "DEN",
+    "MIT", "VON", "AUF", "FUR", "SICH", "DEM", "AUCH", "ALS", "WIR", "WIE", "AN", "NACH", "AUS",
+    "BEI", "WAR", "UBER", "NOCH", "HAT", "WENN", "NUR", "ODER", "ABER", "VOR", "ZUR", "BIS",
+    "MEHR", "DURCH", "MAN", "SEIN", "WIRD", "UNTER", "ZEIT", "JAHR", "UHR", "BERLIN", "NORD",
+    "OST", "SUD", "WEST", "NORDOST", "MAUER", "STADT", "WELT", "LICHT", "SCHATTEN", "ZWISCHEN",
+];
+const FRENCH_WORDS: &[&str] = &[
+    "LE", "LA", "LES", "DE", "DES", "DU", "UN", "UNE", "ET", "EST", "EN", "QUE", "QUI", "DANS",
+    "POUR", "PAS", "PLUS", "PAR", "SUR", "AU", "AVEC", "IL", "ELLE", "NOUS", "VOUS", "ILS", "CE",
+    "CETTE", "SON", "SES", "MAIS", "OU", "COMME", "TOUT", "FAIT", "ETE", "ETRE", "AVOIR", "NORD",
+    "SUD", "OUEST", "LUMIERE", "OMBRE", "HEURE", "HORLOGE", "TEMPS", "MONDE", "VILLE", "ENTRE",
+    "SOUS", "MUR", "BERLIN",
+];
+const RUSSIAN_WORDS: &[&str] = &[
+    "I", "V", "NE", "NA", "YA", "ON", "S", "CHTO", "ETO", "KAK", "PO", "NO", "ONA", "ONI", "MY",
+    "VY", "TAK", "ZA", "OT", "IZ", "DLYA", "BYL", "BYLA", "BYLO", "ESHCHE", "UZHE", "VSE", "TOLKO",
+    "KOGDA", "GDE", "TUT", "TAM", "VOT", "DA", "NET", "SVET", "TEN", "CHASY", "VREMYA", "GOROD",
+    "MIR", "SEVER", "VOSTOK", "ZAPAD", "YUG", "STENA", "BERLIN",
+];
+const LATIN_WORDS: &[&str] = &[
+    "ET", "IN", "EST", "NON", "CUM", "AD", "QUOD", "QUI", "QUAE", "UT", "SED", "SI", "DE", "EX",
+    "PER", "AB", "NEC", "ESSE", "SUNT", "ERAT", "ENIM", "AUTEM", "HIC", "HAEC", "TAMEN", "ETIAM",
+    "POST", "ANTE", "INTER", "SUB", "SUPER", "LUX", "LUCIS", "UMBRA", "TEMPUS", "HORA", "ORIENS",
+    "OCCIDENS", "MURUS", "URBS", "MUNDUS", "DEUS", "REX", "ANNO", "VERITAS", "MEMORIA",
+];
+
+impl Language {
+    pub fn bundled(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        let (letter_frequencies, word_model) = match name.as_str() {
+            "english" => (
+                scoring::english_letter_frequencies(),
+                scoring::default_word_model().clone(),
+            ),
+            "german" => (GERMAN_FREQUENCIES, bundled_words(GERMAN_WORDS)),
+            "french" => (FRENCH_FREQUENCIES, bundled_words(FRENCH_WORDS)),
+            "russian" => (RUSSIAN_FREQUENCIES, bundled_words(RUSSIAN_WORDS)),
+            "latin" => (LATIN_FREQUENCIES, bundled_words(LATIN_WORDS)),
+            _ => return None,
+        };
+        Some(Self {
+            name,
+            letter_frequencies,
+            word_model: Arc::new(word_model),
+            ngram_model: None,
+        })
+    }
+
+    // Loads whatever build-ngrams wrote to the directory: monograms replace
+    // the letter frequencies, words.txt the word model, and the longest
+    // n-gram file present becomes the n-gram model. Languages that are not
+    // bundled need at least monograms.txt.
+    pub fn load<P: AsRef<Path>>(name: &str, dir: P) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        let monograms = dir.join(corpus::ngram_file_name(1));
+        let mut language = match Self::bundled(name) {
+            Some(language) => language,
+            None if monograms.is_file() => Self {
+                name: name.to_ascii_lowercase(),
+                letter_frequencies: [1.0; 26],
+                word_model: Arc::new(scoring::default_word_model().clone()),
+                ngram_model: None,
+            },
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "'{}' is not bundled and {} is missing",
+                        name,
+                        monograms.display()
+                    ),
+                ))
+            }
+        };
+
+        if monograms.is_file() {
+            language.letter_frequencies = statistics::load_distribution(&monograms)?;
+        }
+        let words = dir.join(corpus::WORD_FILE_NAME);
+        if words.is_file() {
+            language.word_model = Arc::new(WordModel::load(&words)?);
+        }
+        if let Some(path) = (2..=MAX_NGRAM_LENGTH)
+            .rev()
+            .map(|n| dir.join(corpus::ngram_file_name(n)))
+            .find(|path| path.is_file())
+        {
+            language.ngram_model = Some(Arc::new(NgramModel::load(path)?));
+        }
+        Ok(language)
+    }
+
+    pub fn fit(&self, text: &str) -> f64 {
+        if let Some(model) = &self.ngram_model {
+            return model.score_per_ngram(text);
+        }
+        let counts = statistics::letter_counts(text);
+        let total: usize = counts.iter().sum();
+        if total == 0 {
+            return f64::NEG_INFINITY;
+        }
+        let frequency_total: f64 = self.letter_frequencies.iter().sum();
+        let sum: f64 = counts
+            .iter()
+            .zip(&self.letter_frequencies)
+            .map(|(&count, &f)| count as f64 * (f.max(1e-6) / frequency_total).log10())
+            .sum();
+        sum / total as f64
+    }
+
+    pub fn model_name(&self) -> String {
+        match &self.ngram_model {
+            Some(model) => format!("{}-grams", model.n()),
+            None => "monograms".to_string(),
+        }
+    }
+
+    pub fn evaluate(&self, text: &str) -> LanguageFit {
+        LanguageFit {
+            language: self.name.clone(),
+            fit: self.fit(text),
+            model: self.model_name(),
+            word_coverage: self
+                .word_model
+                .segment(text)
+                .known_coverage(scoring::MIN_SCORED_WORD_LENGTH),
+        }
+    }
+}
+
+fn bundled_words(words: &[&str]) -> WordModel {
+    WordModel::from_words(words.iter()).unwrap()
+}
+
+// Parses `english,german:models/de,...`: each entry is a language name,
+// optionally followed by the directory to load its models from.
+pub fn parse_languages(spec: &str) -> Result<Vec<Language>, String> {
+    let mut languages = Vec::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let language = match entry.split_once(':') {
+            Some((name, dir)) => Language::load(name, dir)
+                .map_err(|e| format!("unable to load {} from {}: {}", name, dir, e))?,
+            None => Language::bundled(entry).ok_or_else(|| {
+                format!(
+                    "unknown language '{}', expected one of {} or name:dir",
+                    entry,
+                    BUNDLED_LANGUAGES.join(", ")
+                )
+            })?,
+        };
+        languages.push(language);
+    }
+    if languages.is_empty() {
+        return Err("language list is empty".to_string());
+    }
+    // Fits on different models are on different scales, so every language
+    // must be scored the same way for the best fit to mean anything.
+    if let Some(other) = languages
+        .iter()
+        .find(|l| l.model_name() != languages[0].model_name())
+    {
+        return Err(format!(
+            "{} is fitted on {} but {} on {}; give every language the same kind of model",
+            languages[0].name,
+            languages[0].model_name(),
+            other.name,
+            other.model_name()
+        ));
+    }
+    Ok(languages)
+}
+
+// Every language's fit, best first. `parse_languages` only admits lists
+// whose languages are all fitted on the same kind of model.
+pub fn rank_languages(text: &str, languages: &[Language]) -> Vec<LanguageFit> {
+    let mut fits: Vec<LanguageFit> = languages.iter().map(|l| l.evaluate(text)).collect();
+    fits.sort_by(|a, b| b.fit.total_cmp(&a.fit));
+    fits
+}
+
+pub fn best_language(text: &str, languages: &[Language]) -> Option<LanguageFit> {
+    rank_languages(text, languages).into_iter().next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // A build-ngrams style directory with monograms and trigrams.
+    fn model_dir(name: &str, text: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("kryptos-language-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let counts = statistics::letter_counts(text);
+        let monograms: Vec<String> = counts
+            .iter()
+            .enumerate()
+            .map(|(i, count)| format!("{} {}", (b'A' + i as u8) as char, count))
+            .collect();
+        fs::write(dir.join(corpus::ngram_file_name(1)), monograms.join("\n")).unwrap();
+        let trigrams: Vec<String> = text
+            .as_bytes()
+            .windows(3)
+            .map(|w| format!("{} 1", String::from_utf8_lossy(w)))
+            .collect();
+        fs::write(dir.join(corpus::ngram_file_name(3)), trigrams.join("\n")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn parse_languages_rejects_mixed_model_kinds() {
+        assert_eq!(parse_languages("english,german").unwrap().len(), 2);
+        assert!(parse_languages("english,klingon").is_err());
+        assert!(parse_languages(" , ").is_err());
+
+        let dir = model_dir("mixed", "DIEUHRINBERLINZEIGTDIEZEITDERWELT");
+        let spec = format!("english,german:{}", dir.display());
+        let error = parse_languages(&spec).unwrap_err();
+        assert!(error.contains("english is fitted on monograms but german on 3-grams"));
+
+        let spec = format!("english:{0},german:{0}", dir.display());
+        let languages = parse_languages(&spec).unwrap();
+        assert!(languages.iter().all(|l| l.model_name() == "3-grams"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rank_languages_prefers_the_language_of_the_text() {
+        let languages = parse_languages("english,german").unwrap();
+        let ranked = rank_languages("DIEUHRINBERLINZEIGTDIEZEITDERWELTUNDSCHATTEN", &languages);
+        assert_eq!(ranked[0].language, "german");
+        assert_eq!(ranked[0].model, "monograms");
+        let ranked = rank_languages("THEQUICKBROWNFOXJUMPSOVERTHELAZYDOG", &languages);
+        assert_eq!(ranked[0].language, "english");
+    }
+}
@@ -0,0 +1,518 @@
+// layers.rs
+
+use crate::hill_climb::{KeyMove, KeySpace};
+use crate::key_generation::keyed_alphabet;
+use crate::substitution::{self, CipherFamily, Tableau, STRAIGHT_ALPHABET};
+use crate::transposition;
+use rand::Rng;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LayerKind {
+    Columnar,
+    Periodic,
+    Monoalphabetic,
+}
+
+impl LayerKind {
+    pub const ALL: [LayerKind; 3] = [
+        LayerKind::Columnar,
+        LayerKind::Periodic,
+        LayerKind::Monoalphabetic,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LayerKind::Columnar => "columnar",
+            LayerKind::Periodic => "periodic",
+            LayerKind::Monoalphabetic => "monoalphabetic",
+        }
+    }
+}
+
+impl FromStr for LayerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LayerKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| format!("unknown layer kind '{}'", s))
+    }
+}
+
+// One layer of a stacked cipher, described by what it takes to undo it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CipherLayer {
+    // The columns were read off in `order`, so its length is the width.
+    Columnar {
+        order: Vec<usize>,
+    },
+    // A periodic key over a Quagmire III tableau with `alphabet` on both
+    // sides, as for K1 and K2.
+    Periodic {
+        family: CipherFamily,
+        alphabet: String,
+        key: String,
+    },
+    // The plaintext letter for each ciphertext letter A-Z.
+    Monoalphabetic {
+        key: String,
+    },
+}
+
+impl CipherLayer {
+    pub fn kind(&self) -> LayerKind {
+        match self {
+            CipherLayer::Columnar { .. } => LayerKind::Columnar,
+            CipherLayer::Periodic { .. } => LayerKind::Periodic,
+            CipherLayer::Monoalphabetic { .. } => LayerKind::Monoalphabetic,
+        }
+    }
+
+    pub fn decrypt(&self, text: &str) -> String {
+        match self {
+            CipherLayer::Columnar { order } => transposition::columnar_decrypt(text, order),
+            CipherLayer::Periodic {
+                family,
+                alphabet,
+                key,
+            } => match Tableau::new("keyed", alphabet, alphabet) {
+                Ok(tableau) => substitution::periodic_decrypt(text, key, *family, &tableau),
+                Err(_) => text.to_string(),
+            },
+            CipherLayer::Monoalphabetic { key } => {
+                substitution::monoalphabetic_substitution(text, key, STRAIGHT_ALPHABET)
+            }
+        }
+    }
+}
+
+// `columnar:2,0,1`, `periodic:FAMILY:ALPHABET:KEY` or `monoalphabetic:KEY`.
+impl fmt::Display for CipherLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CipherLayer::Columnar { order } => {
+                write!(f, "columnar:{}", transposition::format_permutation(order))
+            }
+            CipherLayer::Periodic {
+                family,
+                alphabet,
+                key,
+            } => write!(f, "periodic:{}:{}:{}", family.name(), alphabet, key),
+            CipherLayer::Monoalphabetic { key } => write!(f, "monoalphabetic:{}", key),
+        }
+    }
+}
+
+impl FromStr for CipherLayer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        match parts.as_slice() {
+            ["columnar", order] => Ok(CipherLayer::Columnar {
+                order: transposition::parse_permutation(order)?,
+            }),
+            ["periodic", family, alphabet, key] => {
+                Tableau::new("keyed", alphabet, alphabet)?;
+                Ok(CipherLayer::Periodic {
+                    family: family.parse()?,
+                    alphabet: alphabet.to_string(),
+                    key: key.to_string(),
+                })
+            }
+            ["monoalphabetic", key] => {
+                Tableau::new("key", key, key)?;
+                Ok(CipherLayer::Monoalphabetic {
+                    key: key.to_string(),
+                })
+            }
+            _ => Err(format!("unknown cipher layer '{}'", s)),
+        }
+    }
+}
+
+// Layers in the order they are taken off: the first one is applied to the
+// ciphertext.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LayerStack {
+    pub layers: Vec<CipherLayer>,
+}
+
+impl LayerStack {
+    pub fn decrypt(&self, ciphertext: &str) -> String {
+        self.layers
+            .iter()
+            .fold(ciphertext.to_string(), |text, layer| layer.decrypt(&text))
+    }
+}
+
+// Layers joined with " > ", e.g. `columnar:1,0 > monoalphabetic:QWERTY...`.
+impl fmt::Display for LayerStack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let layers: Vec<String> = self.layers.iter().map(|l| l.to_string()).collect();
+        write!(f, "{}", layers.join(" > "))
+    }
+}
+
+impl FromStr for LayerStack {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let layers = s
+            .split('>')
+            .map(|layer| layer.trim().parse())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(LayerStack { layers })
+    }
+}
+
+// The configurations a search may build: which layer kinds, how many
+// layers, and how large the keys and transposition widths may grow.
+#[derive(Debug, Clone)]
+pub struct LayerBounds {
+    pub kinds: Vec<LayerKind>,
+    pub max_layers: usize,
+    pub max_width: usize,
+    pub max_key_length: usize,
+    pub families: Vec<CipherFamily>,
+    // Starting alphabets for periodic layers. Mutation may later shuffle
+    // them into alphabets no keyword produces.
+    pub alphabets: Vec<String>,
+}
+
+impl Default for LayerBounds {
+    fn default() -> Self {
+        Self {
+            kinds: LayerKind::ALL.to_vec(),
+            max_layers: 2,
+            max_width: 12,
+            max_key_length: 12,
+            families: CipherFamily::ALL.to_vec(),
+            alphabets: vec![STRAIGHT_ALPHABET.to_string(), keyed_alphabet("KRYPTOS")],
+        }
+    }
+}
+
+impl LayerBounds {
+    pub fn random_layer<R: Rng>(&self, kind: LayerKind, rng: &mut R) -> CipherLayer {
+        match kind {
+            LayerKind::Columnar => {
+                let width = rng.gen_range(2..=self.max_width.max(2));
+                CipherLayer::Columnar {
+                    order: shuffled((0..width).collect(), rng),
+                }
+            }
+            LayerKind::Periodic => {
+                let length = rng.gen_range(1..=self.max_key_length.max(1));
+                CipherLayer::Periodic {
+                    family: self.random_family(rng),
+                    alphabet: self.random_alphabet(rng),
+                    key: KeySpace::periodic(length)
+                        .random_key(rng)
+                        .into_iter()
+                        .collect(),
+                }
+            }
+            LayerKind::Monoalphabetic => CipherLayer::Monoalphabetic {
+                key: KeySpace::alphabet_permutation()
+                    .random_key(rng)
+                    .into_iter()
+                    .collect(),
+            },
+        }
+    }
+
+    pub fn random_kind<R: Rng>(&self, rng: &mut R) -> LayerKind {
+        match self.kinds.as_slice() {
+            [] => LayerKind::Periodic,
+            kinds => kinds[rng.gen_range(0..kinds.len())],
+        }
+    }
+
+    pub fn random_stack<R: Rng>(&self, rng: &mut R) -> LayerStack {
+        let count = rng.gen_range(1..=self.max_layers.max(1));
+        LayerStack {
+            layers: (0..count)
+                .map(|_| self.random_layer(self.random_kind(rng), rng))
+                .collect(),
+        }
+    }
+
+    fn random_family<R: Rng>(&self, rng: &mut R) -> CipherFamily {
+        match self.families.as_slice() {
+            [] => CipherFamily::Vigenere,
+            families => families[rng.gen_range(0..families.len())],
+        }
+    }
+
+    fn random_alphabet<R: Rng>(&self, rng: &mut R) -> String {
+        match self.alphabets.as_slice() {
+            [] => STRAIGHT_ALPHABET.to_string(),
+            alphabets => alphabets[rng.gen_range(0..alphabets.len())].clone(),
+        }
+    }
+
+    // Changes one thing about the layer: a key letter or the order of the
+    // key, the key length or transposition width, the family, or the
+    // alphabet, which is either perturbed or replaced by a starting one.
+    pub fn mutate_layer<R: Rng>(&self, layer: &mut CipherLayer, rng: &mut R) {
+        match layer {
+            CipherLayer::Columnar { order } => {
+                if rng.gen_bool(0.2) {
+                    resize_order(order, self.max_width.max(2), rng);
+                } else if order.len() > 1 {
+                    let i = rng.gen_range(0..order.len());
+                    let j = rng.gen_range(0..order.len());
+                    order.swap(i, j);
+                }
+            }
+            CipherLayer::Periodic {
+                family,
+                alphabet,
+                key,
+            } => match rng.gen_range(0..10) {
+                0 => *family = self.random_family(rng),
+                1 => *alphabet = self.random_alphabet(rng),
+                2 => {
+                    let mut letters: Vec<char> = alphabet.chars().collect();
+                    mutate_key(&KeySpace::alphabet_permutation(), &mut letters, rng);
+                    *alphabet = letters.into_iter().collect();
+                }
+                3 => {
+                    let mut letters: Vec<char> = key.chars().collect();
+                    if letters.len() < self.max_key_length && rng.gen_bool(0.5) {
+                        letters.insert(rng.gen_range(0..=letters.len()), random_letter(rng));
+                    } else if letters.len() > 1 {
+                        letters.remove(rng.gen_range(0..letters.len()));
+                    }
+                    *key = letters.into_iter().collect();
+                }
+                _ => {
+                    let mut letters: Vec<char> = key.chars().collect();
+                    mutate_key(&KeySpace::periodic(letters.len()), &mut letters, rng);
+                    *key = letters.into_iter().collect();
+                }
+            },
+            CipherLayer::Monoalphabetic { key } => {
+                let mut letters: Vec<char> = key.chars().collect();
+                mutate_key(&KeySpace::alphabet_permutation(), &mut letters, rng);
+                *key = letters.into_iter().collect();
+            }
+        }
+    }
+
+    // Mostly mutates one layer; otherwise replaces, inserts, removes or
+    // reorders whole layers within the bounds.
+    pub fn mutate_stack<R: Rng>(&self, stack: &mut LayerStack, rng: &mut R) {
+        let layers = &mut stack.layers;
+        if layers.is_empty() {
+            *stack = self.random_stack(rng);
+            return;
+        }
+        match rng.gen_range(0..10) {
+            0 => {
+                let i = rng.gen_range(0..layers.len());
+                layers[i] = self.random_layer(self.random_kind(rng), rng);
+            }
+            1 if layers.len() < self.max_layers => {
+                let layer = self.random_layer(self.random_kind(rng), rng);
+                layers.insert(rng.gen_range(0..=layers.len()), layer);
+            }
+            2 if layers.len() > 1 => {
+                layers.remove(rng.gen_range(0..layers.len()));
+            }
+            3 if layers.len() > 1 => {
+                let i = rng.gen_range(0..layers.len() - 1);
+                layers.swap(i, i + 1);
+            }
+            _ => {
+                let i = rng.gen_range(0..layers.len());
+                self.mutate_layer(&mut layers[i], rng);
+            }
+        }
+    }
+
+    // Stacks of the same depth exchange layers position by position, and
+    // aligned layers of the same shape mix their keys. Stacks of different
+    // depths are spliced: the head of one and the tail of the other.
+    pub fn crossover<R: Rng>(&self, a: &LayerStack, b: &LayerStack, rng: &mut R) -> LayerStack {
+        if a.layers.len() == b.layers.len() {
+            return LayerStack {
+                layers: a
+                    .layers
+                    .iter()
+                    .zip(&b.layers)
+                    .map(|(x, y)| {
+                        cross_layers(x, y, rng).unwrap_or_else(|| {
+                            if rng.gen_bool(0.5) {
+                                x.clone()
+                            } else {
+                                y.clone()
+                            }
+                        })
+                    })
+                    .collect(),
+            };
+        }
+
+        let head = rng.gen_range(1..=a.layers.len());
+        let tail = rng.gen_range(0..b.layers.len());
+        let mut layers: Vec<CipherLayer> = a.layers[..head]
+            .iter()
+            .chain(&b.layers[tail..])
+            .cloned()
+            .collect();
+        layers.truncate(self.max_layers.max(1));
+        LayerStack { layers }
+    }
+}
+
+fn cross_layers<R: Rng>(a: &CipherLayer, b: &CipherLayer, rng: &mut R) -> Option<CipherLayer> {
+    match (a, b) {
+        (CipherLayer::Columnar { order: x }, CipherLayer::Columnar { order: y })
+            if x.len() == y.len() =>
+        {
+            Some(CipherLayer::Columnar {
+                order: order_crossover(x, y, rng),
+            })
+        }
+        (
+            CipherLayer::Periodic {
+                family,
+                alphabet,
+                key: x,
+            },
+            CipherLayer::Periodic {
+                family: other_family,
+                alphabet: other_alphabet,
+                key: y,
+            },
+        ) if x.len() == y.len() => Some(CipherLayer::Periodic {
+            family: if rng.gen_bool(0.5) {
+                *family
+            } else {
+                *other_family
+            },
+            alphabet: if rng.gen_bool(0.5) {
+                alphabet.clone()
+            } else {
+                other_alphabet.clone()
+            },
+            key: x
+                .chars()
+                .zip(y.chars())
+                .map(|(p, q)| if rng.gen_bool(0.5) { p } else { q })
+                .collect(),
+        }),
+        (CipherLayer::Monoalphabetic { key: x }, CipherLayer::Monoalphabetic { key: y }) => {
+            let x: Vec<char> = x.chars().collect();
+            let y: Vec<char> = y.chars().collect();
+            Some(CipherLayer::Monoalphabetic {
+                key: order_crossover(&x, &y, rng).into_iter().collect(),
+            })
+        }
+        _ => None,
+    }
+}
+
+// Order crossover for permutations: a slice of `a` stays in place and the
+// remaining positions take the missing items in the order `b` has them.
+fn order_crossover<T: Copy + PartialEq, R: Rng>(a: &[T], b: &[T], rng: &mut R) -> Vec<T> {
+    if a.len() < 2 {
+        return a.to_vec();
+    }
+    let i = rng.gen_range(0..a.len());
+    let j = rng.gen_range(0..a.len());
+    let (lo, hi) = (i.min(j), i.max(j));
+
+    let kept = &a[lo..=hi];
+    let mut rest = b.iter().filter(|item| !kept.contains(item));
+    (0..a.len())
+        .map(|k| {
+            if (lo..=hi).contains(&k) {
+                a[k]
+            } else {
+                *rest.next().unwrap_or(&a[k])
+            }
+        })
+        .collect()
+}
+
+// Widens the transposition by a column in a random reading position, or
+// narrows it by dropping its last column.
+fn resize_order<R: Rng>(order: &mut Vec<usize>, max_width: usize, rng: &mut R) {
+    let width = order.len();
+    if width < max_width && (width <= 2 || rng.gen_bool(0.5)) {
+        order.insert(rng.gen_range(0..=width), width);
+    } else if width > 2 {
+        order.retain(|&column| column != width - 1);
+    }
+}
+
+fn mutate_key<R: Rng>(space: &KeySpace, key: &mut [char], rng: &mut R) {
+    for _ in 0..8 {
+        let mv = KeyMove::ALL[rng.gen_range(0..KeyMove::ALL.len())];
+        if space.mutate(key, mv, rng) {
+            return;
+        }
+    }
+}
+
+fn random_letter<R: Rng>(rng: &mut R) -> char {
+    (b'A' + rng.gen_range(0..26u8)) as char
+}
+
+fn shuffled<T, R: Rng>(mut items: Vec<T>, rng: &mut R) -> Vec<T> {
+    for i in (1..items.len()).rev() {
+        items.swap(i, rng.gen_range(0..=i));
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeding;
+
+    #[test]
+    fn stacks_display_and_parse_round_trip() {
+        let spec = "columnar:2,0,1 > periodic:vigenere:KRYPTOSABCDEFGHIJLMNQUVWXZ:PALIMPSEST";
+        let stack: LayerStack = spec.parse().unwrap();
+        assert_eq!(stack.layers.len(), 2);
+        assert_eq!(stack.to_string(), spec);
+        assert!("periodic:vigenere:AAB:KEY".parse::<LayerStack>().is_err());
+        assert!("rail:3".parse::<LayerStack>().is_err());
+    }
+
+    #[test]
+    fn mutation_and_crossover_stay_within_bounds() {
+        let bounds = LayerBounds {
+            max_layers: 3,
+            max_width: 5,
+            max_key_length: 4,
+            ..LayerBounds::default()
+        };
+        let mut rng = seeding::rng(8);
+        let within = |stack: &LayerStack| {
+            (1..=3).contains(&stack.layers.len())
+                && stack.layers.iter().all(|layer| match layer {
+                    CipherLayer::Columnar { order } => {
+                        (2..=5).contains(&order.len()) && transposition::is_permutation(order)
+                    }
+                    CipherLayer::Periodic { key, .. } => (1..=4).contains(&key.len()),
+                    CipherLayer::Monoalphabetic { key } => key.len() == 26,
+                })
+        };
+        let mut a = bounds.random_stack(&mut rng);
+        let mut b = bounds.random_stack(&mut rng);
+        for _ in 0..500 {
+            bounds.mutate_stack(&mut a, &mut rng);
+            bounds.mutate_stack(&mut b, &mut rng);
+            let child = bounds.crossover(&a, &b, &mut rng);
+            assert!(within(&a) && within(&b) && within(&child), "{}", child);
+        }
+    }
+}
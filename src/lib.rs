@@ -0,0 +1,30 @@
+pub mod alphabet_analysis;
+pub mod annealing;
+pub mod beam;
+pub mod checkpoint;
+pub mod cipher_id;
+pub mod constraints;
+pub mod corpus;
+pub mod crib_drag;
+pub mod cribs;
+pub mod decryption;
+pub mod genetic;
+pub mod hill_climb;
+pub mod kasiski;
+pub mod key_generation;
+pub mod known_plaintext;
+pub mod language;
+pub mod layers;
+pub mod ngram;
+pub mod preprocessing;
+pub mod quagmire;
+pub mod scorer;
+pub mod scoring;
+pub mod seeding;
+pub mod segmentation;
+pub mod significance;
+pub mod slicing;
+pub mod statistics;
+pub mod store;
+pub mod substitution;
+pub mod transposition;
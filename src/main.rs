@@ -1,59 +1,1218 @@
-use decryption::Candidate;
+use cli::Options;
+use kryptos_rs::annealing::{self, AlphabetSearch, AnnealingSchedule, KeySearch, SearchSpace};
+use kryptos_rs::beam::{self, BeamSearch};
+use kryptos_rs::checkpoint::{self, SweepCheckpoint};
+use kryptos_rs::cipher_id::{self, Identifier};
+use kryptos_rs::constraints::CribFilter;
+use kryptos_rs::corpus;
+use kryptos_rs::crib_drag::{self, Dictionary};
+use kryptos_rs::cribs::{k4_cribs, Crib};
+use kryptos_rs::decryption::Candidate;
+use kryptos_rs::genetic::{self, GeneticSearch};
+use kryptos_rs::hill_climb::{self, HillClimbing, KeyedCipher};
+use kryptos_rs::kasiski;
+use kryptos_rs::key_generation::{self, generate_keywords_from_wordlist, keyed_alphabet};
+use kryptos_rs::known_plaintext;
+use kryptos_rs::language::{self, Language};
+use kryptos_rs::layers::{LayerBounds, LayerKind};
+use kryptos_rs::ngram::{self, NgramModel};
+use kryptos_rs::preprocessing::{self, RemovalPattern};
+use kryptos_rs::quagmire::{self, QuagmireSolver};
+use kryptos_rs::scorer::{self, Scorer, ScorerResources, WeightedScorer};
+use kryptos_rs::seeding;
+use kryptos_rs::segmentation::{Anomaly, Segmentation, WordModel};
+use kryptos_rs::significance::{self, Baseline, CalibrationConfig};
+use kryptos_rs::slicing;
+use kryptos_rs::store::{self, ResultStore, RunRecord};
+use kryptos_rs::substitution::{CipherFamily, Tableau};
+use kryptos_rs::transposition::{self, PermutationAnnealing, PermutationSearch};
+use kryptos_rs::{scoring, statistics, substitution};
 use rayon::prelude::*;
 use std::cmp::Reverse;
-use std::collections::BinaryHeap;
-use std::fs;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{env, fs, process};
 
-mod alphabet_analysis;
-mod decryption;
-mod key_generation;
-mod scoring;
-mod substitution;
+mod cli;
 
-use crate::key_generation::generate_keywords_from_wordlist;
+const COMMANDS: &[&str] = &[
+    "sweep",
+    "permute",
+    "anneal-transposition",
+    "known-plaintext",
+    "crib-drag",
+    "build-ngrams",
+    "segment",
+    "calibrate",
+    "stats",
+    "kasiski",
+    "identify",
+    "slice",
+    "languages",
+    "hill-climb",
+    "anneal",
+    "evolve",
+    "beam",
+    "quagmire",
+    "runs",
+    "diff-runs",
+    "tested",
+];
+
+// The commands whose runs go to the result store.
+const SEARCH_COMMANDS: &[&str] = &[
+    "sweep",
+    "anneal-transposition",
+    "hill-climb",
+    "anneal",
+    "evolve",
+    "beam",
+    "quagmire",
+];
+
+// The sweep's base alphabet, which each keyword substitutes into one of the
+// alphabets it tries.
+const SWEEP_ALPHABET: &str = "KRYPTOSABCDEFGHIJLNGHIJLMNQUVWXZ";
+
+// Alphabets the sweep scores between checkpoint opportunities.
+const SWEEP_CHUNK: usize = 64;
 
 fn main() {
-    // Read the K4 ciphertext from a file
-    let ciphertext = fs::read_to_string("k4_ciphertext.txt").expect("Unable to read file");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = Options::parse(&args);
+
+    // Searches add their effective settings and top candidates to the record,
+    // which is then saved to the result store.
+    let mut record = RunRecord::new(&options.command, options.entries());
+    let started = Instant::now();
+
+    match options.command.as_str() {
+        "sweep" => run_sweep(&options, &mut record),
+        "permute" => run_permute(&options),
+        "anneal-transposition" => run_anneal_transposition(&options, &mut record),
+        "known-plaintext" => run_known_plaintext(&options),
+        "crib-drag" => run_crib_drag(&options),
+        "build-ngrams" => run_build_ngrams(&options),
+        "segment" => run_segment(&options),
+        "calibrate" => run_calibrate(&options),
+        "stats" => run_stats(&options),
+        "kasiski" => run_kasiski(&options),
+        "identify" => run_identify(&options),
+        "slice" => run_slice(&options),
+        "languages" => run_languages(&options),
+        "hill-climb" => run_hill_climb(&options, &mut record),
+        "anneal" => run_anneal(&options, &mut record),
+        "evolve" => run_evolve(&options, &mut record),
+        "beam" => run_beam(&options, &mut record),
+        "quagmire" => run_quagmire(&options, &mut record),
+        "runs" => run_runs(&options),
+        "diff-runs" => run_diff_runs(&options),
+        "tested" => run_tested(&options),
+        other => {
+            eprintln!("Unknown command: {}", other);
+            eprintln!("Commands: {}", COMMANDS.join(", "));
+            process::exit(2);
+        }
+    }
+
+    record.seconds = started.elapsed().as_secs_f64();
+    store_run(&options, &mut record);
+}
+
+fn read_ciphertext(options: &Options) -> String {
+    let path = options.value("ciphertext").unwrap_or("k4_ciphertext.txt");
+    fs::read_to_string(path)
+        .expect("Unable to read file")
+        .trim()
+        .to_string()
+}
+
+fn read_cribs(options: &Options) -> Vec<Crib> {
+    if options.switch("no-cribs") {
+        Vec::new()
+    } else {
+        k4_cribs()
+    }
+}
+
+// The word model from `--words <file>` (`WORD COUNT` lines, as written by
+// build-ngrams), or the uniform model over the built-in common words.
+fn read_word_model(options: &Options) -> WordModel {
+    match options.value("words") {
+        Some(path) => WordModel::load(path).unwrap_or_else(|e| {
+            eprintln!("Unable to load words from {}: {}", path, e);
+            process::exit(2);
+        }),
+        None => scoring::default_word_model().clone(),
+    }
+}
+
+// The fitness function from `--scorer name[:weight],...` (see
+// `scorer::SCORER_NAMES`). Without a spec, candidates are scored by n-grams
+// when `--ngrams <file>` is given and by `scoring::score_text` otherwise.
+fn read_scorer(options: &Options) -> WeightedScorer {
+    build_scorer(options, &read_scorer_resources(options))
+}
+
+// What `read_scorer` builds from, for callers that rebuild the scorer with
+// other cribs, such as the sweep under each removal hypothesis.
+fn read_scorer_resources(options: &Options) -> ScorerResources {
+    let ngram_model = options.value("ngrams").map(|path| {
+        Arc::new(NgramModel::load(path).unwrap_or_else(|e| {
+            eprintln!("Unable to load n-grams from {}: {}", path, e);
+            process::exit(2);
+        }))
+    });
+    ScorerResources {
+        ngram_model,
+        word_model: Arc::new(read_word_model(options)),
+        cribs: read_cribs(options),
+        reference: read_reference(options),
+        languages: read_languages(options),
+    }
+}
+
+fn build_scorer(options: &Options, resources: &ScorerResources) -> WeightedScorer {
+    let default_spec = if resources.ngram_model.is_some() {
+        "ngram"
+    } else {
+        "legacy"
+    };
+    let spec = options.value("scorer").unwrap_or(default_spec);
+    scorer::build_scorer(spec, resources).unwrap_or_else(|e| {
+        eprintln!("Invalid --scorer: {}", e);
+        process::exit(2);
+    })
+}
+
+// The reference letter distribution from `--reference`: english, uniform,
+// or a monograms file written by build-ngrams.
+fn read_reference(options: &Options) -> [f64; 26] {
+    match options.value("reference").unwrap_or("english") {
+        "english" => scoring::english_letter_frequencies(),
+        "uniform" => [1.0; 26],
+        path => statistics::load_distribution(path).unwrap_or_else(|e| {
+            eprintln!("Unable to load letter distribution from {}: {}", path, e);
+            process::exit(2);
+        }),
+    }
+}
+
+// Languages from `--languages english,german:models/de,...`, defaulting to
+// the bundled English model.
+fn read_languages(options: &Options) -> Vec<Language> {
+    language::parse_languages(options.value("languages").unwrap_or("english")).unwrap_or_else(|e| {
+        eprintln!("Invalid --languages: {}", e);
+        process::exit(2);
+    })
+}
+
+// The seed of a randomized search from `--seed N`, or a fresh one. It is
+// printed either way so the run can be replayed.
+fn read_seed(options: &Options) -> u64 {
+    let seed = options.parsed("seed", seeding::random_seed());
+    println!("Seed: {}", seed);
+    seed
+}
+
+// Alphabet keywords from `--keywords A,B,C` and/or one per line in
+// `--keyword-file`, defaulting to KRYPTOS.
+fn read_keywords(options: &Options) -> Vec<String> {
+    let mut keywords: Vec<String> = options
+        .value("keywords")
+        .map(|list| {
+            list.split(',')
+                .map(|k| k.trim().to_ascii_uppercase())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if let Some(path) = options.value("keyword-file") {
+        let contents = fs::read_to_string(path).expect("Unable to read keyword file");
+        keywords.extend(
+            contents
+                .lines()
+                .map(|line| line.trim().to_ascii_uppercase()),
+        );
+    }
+
+    let mut seen = HashSet::new();
+    keywords.retain(|k| {
+        !k.is_empty() && k.chars().all(|c| c.is_ascii_uppercase()) && seen.insert(k.clone())
+    });
+    if keywords.is_empty() {
+        keywords.push("KRYPTOS".to_string());
+    }
+    keywords
+}
+
+// The removal hypotheses to try, with None standing for the untouched
+// ciphertext. Patterns come from `--remove every:N[:OFFSET];at:I,J`,
+// `--remove-every-max N` and `--remove-count K`.
+fn read_removal_patterns(options: &Options, length: usize) -> Vec<Option<RemovalPattern>> {
+    let mut patterns: Vec<Option<RemovalPattern>> = Vec::new();
+
+    if let Some(spec) = options.value("remove") {
+        for part in spec.split(';').filter(|p| !p.is_empty()) {
+            let pattern = part.parse::<RemovalPattern>().unwrap_or_else(|e| {
+                eprintln!("Invalid --remove: {}", e);
+                process::exit(2);
+            });
+            patterns.push(Some(pattern));
+        }
+    }
+    if let Some(max_n) = options.value("remove-every-max") {
+        let max_n = max_n.parse::<usize>().unwrap_or_else(|e| {
+            eprintln!("Invalid --remove-every-max: {}", e);
+            process::exit(2);
+        });
+        patterns.extend(
+            preprocessing::enumerate_every_nth(2, max_n)
+                .into_iter()
+                .map(Some),
+        );
+    }
+    if let Some(count) = options.value("remove-count") {
+        let count = count.parse::<usize>().unwrap_or_else(|e| {
+            eprintln!("Invalid --remove-count: {}", e);
+            process::exit(2);
+        });
+        patterns.extend(
+            preprocessing::enumerate_index_removals(length, count)
+                .into_iter()
+                .map(Some),
+        );
+    }
+
+    if patterns.is_empty() {
+        patterns.push(None);
+    }
+    patterns
+}
+
+fn run_permute(options: &Options) {
+    let ciphertext = read_ciphertext(options);
+    let spec = options.value("permutation").unwrap_or_else(|| {
+        eprintln!("permute requires --permutation i0,i1,...");
+        process::exit(2);
+    });
+
+    let mut permutation = transposition::parse_permutation(spec).unwrap_or_else(|e| {
+        eprintln!("Invalid permutation: {}", e);
+        process::exit(2);
+    });
+    if permutation.len() != ciphertext.len() {
+        eprintln!(
+            "Permutation has {} entries but the ciphertext has {} letters",
+            permutation.len(),
+            ciphertext.len()
+        );
+        process::exit(2);
+    }
+    if options.switch("inverse") {
+        permutation = transposition::invert_permutation(&permutation);
+    }
+
+    let scorer = read_scorer(options);
+    println!("Scorer: {}", scorer.name());
+    let plaintext = transposition::apply_permutation(&ciphertext, &permutation);
+    println!(
+        "Score: {:.8}, Plaintext: '{}'",
+        scorer.score(&plaintext),
+        plaintext
+    );
+    print!("{}", scorer.explain(&plaintext));
+}
+
+fn run_anneal_transposition(options: &Options, record: &mut RunRecord) {
+    let ciphertext = read_ciphertext(options);
+    let cribs = read_cribs(options);
+
+    let defaults = PermutationAnnealing::default();
+    let config = PermutationAnnealing {
+        iterations: options.parsed("iterations", defaults.iterations),
+        initial_temperature: options.parsed("temperature", defaults.initial_temperature),
+        cooling_rate: options.parsed("cooling", defaults.cooling_rate),
+        restarts: options.parsed("restarts", defaults.restarts),
+        pinning: options.parsed("pin", defaults.pinning),
+        seed: read_seed(options),
+    };
+
+    record.set("seed", config.seed);
+
+    let scorer = read_scorer(options);
+    match transposition::anneal_permutation(&ciphertext, &cribs, &config, &scorer) {
+        Some(result) => {
+            let permutation = transposition::format_permutation(&result.permutation);
+            println!("Best Score: {:.8}", result.score);
+            println!("Permutation: {}", permutation);
+            println!("Plaintext: '{}'", result.plaintext);
+            record.add(result.score, &permutation, "", &result.plaintext);
+            print!("{}", scorer.explain(&result.plaintext));
+        }
+        None => {
+            eprintln!(
+                "The ciphertext does not contain enough letters to place the cribs; \
+                 use --pin positions to keep the crib positions in place instead"
+            );
+            process::exit(1);
+        }
+    }
+}
+
+// `--cipher periodic` (the default, using --family and --tableau) or
+// `--cipher monoalphabetic`.
+fn read_keyed_cipher(options: &Options) -> KeyedCipher {
+    match options.value("cipher").unwrap_or("periodic") {
+        "periodic" => KeyedCipher::Periodic {
+            family: options.parsed("family", CipherFamily::Vigenere),
+            tableau: Box::new(options.parsed("tableau", Tableau::straight())),
+        },
+        "monoalphabetic" => KeyedCipher::Monoalphabetic,
+        other => {
+            eprintln!(
+                "Invalid --cipher: '{}', expected periodic or monoalphabetic",
+                other
+            );
+            process::exit(2);
+        }
+    }
+}
+
+fn run_hill_climb(options: &Options, record: &mut RunRecord) {
+    let ciphertext = read_ciphertext(options);
+    let cipher = read_keyed_cipher(options);
+    let space = cipher.key_space(options.parsed("key-length", 8));
+
+    let defaults = HillClimbing::default();
+    let config = HillClimbing {
+        iterations: options.parsed("iterations", defaults.iterations),
+        patience: options.parsed("patience", defaults.patience),
+        restarts: options.parsed("restarts", defaults.restarts),
+        seed: read_seed(options),
+    };
+    record.set("seed", config.seed);
+
+    let scorer = read_scorer(options);
+    let results = hill_climb::hill_climb(
+        &space,
+        &config,
+        |key| cipher.decrypt(&ciphertext, key),
+        &scorer,
+    );
+
+    println!("Restart summits:");
+    for result in results.iter().take(options.parsed("top", 5)) {
+        println!(
+            "  Score: {:.8}, Key: {}, Plaintext: '{}'",
+            result.score, result.key, result.plaintext
+        );
+        record.add(result.score, &result.key, "", &result.plaintext);
+    }
+    if let Some(best) = results.first() {
+        print!("{}", scorer.explain(&best.plaintext));
+    }
+}
+
+// `--space keys` anneals the key of --cipher, `--space alphabet` the keyed
+// alphabet of a Quagmire III tableau under a fixed --key, and
+// `--space permutation` a transposition with the cribs pinned.
+fn run_anneal(options: &Options, record: &mut RunRecord) {
+    let ciphertext = read_ciphertext(options);
+    let defaults = AnnealingSchedule::default();
+    let schedule = AnnealingSchedule {
+        initial_temperature: options.parsed("temperature", defaults.initial_temperature),
+        cooling: options.parsed("cooling", defaults.cooling),
+        iterations: options.parsed("iterations", defaults.iterations),
+        chains: options.parsed("chains", defaults.chains),
+        seed: read_seed(options),
+    };
+    record.set("seed", schedule.seed);
+    let top_n = options.parsed("top", 10);
+
+    match options.value("space").unwrap_or("keys") {
+        "keys" => {
+            let cipher = read_keyed_cipher(options);
+            let space = cipher.key_space(options.parsed("key-length", 8));
+            let search = KeySearch {
+                ciphertext,
+                cipher,
+                space,
+            };
+            run_annealing_search(options, record, &search, &schedule, top_n);
+        }
+        "alphabet" => {
+            let search = AlphabetSearch {
+                ciphertext,
+                family: options.parsed("family", CipherFamily::Vigenere),
+                key: options
+                    .value("key")
+                    .unwrap_or("KRYPTOS")
+                    .to_ascii_uppercase(),
+            };
+            run_annealing_search(options, record, &search, &schedule, top_n);
+        }
+        "permutation" => {
+            let search = PermutationSearch::new(
+                &ciphertext,
+                &read_cribs(options),
+                options.parsed("pin", PermutationAnnealing::default().pinning),
+            );
+            run_annealing_search(options, record, &search, &schedule, top_n);
+        }
+        other => {
+            eprintln!(
+                "Invalid --space: '{}', expected keys, alphabet or permutation",
+                other
+            );
+            process::exit(2);
+        }
+    }
+}
+
+fn run_annealing_search<S: SearchSpace>(
+    options: &Options,
+    record: &mut RunRecord,
+    search: &S,
+    schedule: &AnnealingSchedule,
+    top_n: usize,
+) {
+    let scorer = read_scorer(options);
+    let top_candidates = Mutex::new(BinaryHeap::new());
+    annealing::anneal(search, schedule, &scorer, &top_candidates, top_n);
+
+    let mut candidates: Vec<Candidate> = top_candidates
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|Reverse(candidate)| candidate)
+        .collect();
+    candidates.sort_by(|a, b| b.cmp(a));
+
+    println!("Top candidates over {} chains:", schedule.chains);
+    for candidate in &candidates {
+        println!(
+            "Score: {:.8}, Key: {}, Alphabet: {}, Plaintext: '{}'",
+            candidate.score, candidate.keyword, candidate.alphabet, candidate.plaintext
+        );
+        record.add(
+            candidate.score,
+            &candidate.keyword,
+            &candidate.alphabet,
+            &candidate.plaintext,
+        );
+    }
+    if let Some(best) = candidates.first() {
+        print!("{}", scorer.explain(&best.plaintext));
+    }
+}
+
+// A comma-separated `--name a,b,c`, or the default when absent.
+fn read_list<T: FromStr>(options: &Options, name: &str, default: Vec<T>) -> Vec<T>
+where
+    T::Err: std::fmt::Display,
+{
+    match options.value(name) {
+        Some(list) => list
+            .split(',')
+            .map(|item| {
+                item.trim().parse().unwrap_or_else(|e| {
+                    eprintln!("Invalid value for --{}: {} ({})", name, item, e);
+                    process::exit(2);
+                })
+            })
+            .collect(),
+        None => default,
+    }
+}
+
+// The layer stacks a search may build. Periodic layers start from the
+// straight alphabet and the alphabets keyed by --keywords.
+fn read_layer_bounds(options: &Options, defaults: LayerBounds) -> LayerBounds {
+    // A keyword can key the straight alphabet or repeat another's alphabet.
+    let mut seen = HashSet::new();
+    let alphabets: Vec<String> = std::iter::once(substitution::STRAIGHT_ALPHABET.to_string())
+        .chain(read_keywords(options).iter().map(|k| keyed_alphabet(k)))
+        .filter(|alphabet| seen.insert(alphabet.clone()))
+        .collect();
+
+    LayerBounds {
+        kinds: read_list(options, "kinds", defaults.kinds),
+        max_layers: options.parsed("max-layers", defaults.max_layers),
+        max_width: options.parsed("max-width", defaults.max_width),
+        max_key_length: options.parsed("max-key-length", defaults.max_key_length),
+        families: read_list(options, "families", defaults.families),
+        alphabets,
+    }
+}
+
+fn run_evolve(options: &Options, record: &mut RunRecord) {
+    let ciphertext = read_ciphertext(options);
+    let bounds = read_layer_bounds(options, LayerBounds::default());
+
+    let defaults = GeneticSearch::default();
+    let config = GeneticSearch {
+        population: options.parsed("population", defaults.population),
+        generations: options.parsed("generations", defaults.generations),
+        crossover_rate: options.parsed("crossover-rate", defaults.crossover_rate),
+        mutation_rate: options.parsed("mutation-rate", defaults.mutation_rate),
+        elites: options.parsed("elites", defaults.elites),
+        tournament: options.parsed("tournament", defaults.tournament),
+    };
+
+    let scorer = read_scorer(options);
+    println!("Scorer: {}", scorer.name());
+    let seed = read_seed(options);
+    record.set("seed", seed);
+    let report_every = (config.generations / 20).max(1);
+    let population = genetic::evolve(
+        &ciphertext,
+        &bounds,
+        &config,
+        &scorer,
+        &mut seeding::rng(seed),
+        |generation, best| {
+            if (generation + 1).is_multiple_of(report_every) {
+                println!(
+                    "Generation {}: Best Score: {:.8}, Layers: {}, Plaintext: '{}'",
+                    generation + 1,
+                    best.score,
+                    best.stack,
+                    best.plaintext
+                );
+            }
+        },
+    );
+
+    println!("Final population:");
+    let survivors = genetic::distinct(&population);
+    for individual in survivors.iter().take(options.parsed("top", 10)) {
+        println!(
+            "  Score: {:.8}, Layers: {}, Plaintext: '{}'",
+            individual.score, individual.stack, individual.plaintext
+        );
+        record.add(
+            individual.score,
+            &individual.stack.to_string(),
+            "",
+            &individual.plaintext,
+        );
+    }
+    if let Some(best) = survivors.first() {
+        print!("{}", scorer.explain(&best.plaintext));
+    }
+}
+
+// Takes off the layers named by `--layers`, in decryption order. The beam
+// tries every column order, so the default width is kept small.
+fn run_beam(options: &Options, record: &mut RunRecord) {
+    let ciphertext = read_ciphertext(options);
+    let kinds = read_list(
+        options,
+        "layers",
+        vec![LayerKind::Columnar, LayerKind::Periodic],
+    );
+    let bounds = read_layer_bounds(
+        options,
+        LayerBounds {
+            max_width: 7,
+            ..LayerBounds::default()
+        },
+    );
+
+    let defaults = BeamSearch::default();
+    let config = BeamSearch {
+        width: options.parsed("beam-width", defaults.width),
+        reference: read_reference(options),
+        climb: HillClimbing {
+            iterations: options.parsed("iterations", defaults.climb.iterations),
+            patience: options.parsed("patience", defaults.climb.patience),
+            restarts: options.parsed("restarts", defaults.climb.restarts),
+            seed: read_seed(options),
+        },
+    };
+    record.set("seed", config.climb.seed);
+
+    let scorer = read_scorer(options);
+    println!("Scorer: {}", scorer.name());
+    let beam = beam::beam_search(
+        &ciphertext,
+        &kinds,
+        &bounds,
+        &config,
+        &scorer,
+        |stage, statistic, beam| {
+            if let Some(best) = beam.first() {
+                println!(
+                    "Stage {} ({}, rated by {}): Best: {:.8}, Layers: {}, Text: '{}'",
+                    stage + 1,
+                    kinds[stage].name(),
+                    statistic,
+                    best.score,
+                    best.stack,
+                    best.text
+                );
+            }
+        },
+    );
+
+    println!("Final beam:");
+    for entry in beam.iter().take(options.parsed("top", 10)) {
+        println!(
+            "  Score: {:.8}, Layers: {}, Plaintext: '{}'",
+            entry.score, entry.stack, entry.text
+        );
+        record.add(entry.score, &entry.stack.to_string(), "", &entry.text);
+    }
+    if let Some(best) = beam.first() {
+        print!("{}", scorer.explain(&best.text));
+    }
+}
+
+fn run_quagmire(options: &Options, record: &mut RunRecord) {
+    let ciphertext = read_ciphertext(options);
+    let defaults = QuagmireSolver::default();
+    let config = QuagmireSolver {
+        quagmire: options.parsed("quagmire", defaults.quagmire),
+        family: options.parsed("family", defaults.family),
+        key_length: options.parsed("key-length", defaults.key_length),
+        iterations: options.parsed("iterations", defaults.iterations),
+        patience: options.parsed("patience", defaults.patience),
+        restarts: options.parsed("restarts", defaults.restarts),
+        reference: read_reference(options),
+        max_keyword_length: options.parsed("max-keyword-length", defaults.max_keyword_length),
+        seeds: read_keywords(options)
+            .iter()
+            .map(|k| keyed_alphabet(k))
+            .collect(),
+        seed: read_seed(options),
+    };
+    record.set("seed", config.seed);
+
+    let scorer = read_scorer(options);
+    let solutions = quagmire::solve(&ciphertext, &config, &scorer);
+
+    println!("Climb summits:");
+    for solution in solutions.iter().take(options.parsed("top", 5)) {
+        println!(
+            "  Score: {:.8}, Key: {}, Alphabet: {}, Plaintext: '{}'",
+            solution.score,
+            solution.key(config.quagmire),
+            solution.alphabet,
+            solution.plaintext
+        );
+        record.add(
+            solution.score,
+            &solution.key(config.quagmire),
+            &solution.alphabet,
+            &solution.plaintext,
+        );
+    }
+    let Some(best) = solutions.first() else {
+        return;
+    };
+
+    // The row is only recovered up to rotation, so the derivation is read
+    // from whichever rotation fits a keyword best.
+    let derivation = &best.derivation;
+    let alphabet = derivation.alphabet();
+    if derivation.is_exact() {
+        println!(
+            "Keyword-derivable: {} (alphabet rotated left by {})",
+            if derivation.keyword.is_empty() {
+                "straight alphabet"
+            } else {
+                &derivation.keyword
+            },
+            derivation.rotation
+        );
+    } else {
+        println!(
+            "Not keyword-derivable; closest keyword {} leaves {} letter(s) out of order",
+            derivation.keyword, derivation.misplaced
+        );
+    }
+    if let Some((key, plaintext, score)) =
+        quagmire::rekey(&ciphertext, &config, &alphabet, &best.shifts, &scorer)
+    {
+        println!(
+            "Under {}: Score: {:.8}, Key: {}, Plaintext: '{}'",
+            alphabet, score, key, plaintext
+        );
+    }
+    print!("{}", scorer.explain(&best.plaintext));
+}
+
+fn run_known_plaintext(options: &Options) {
+    let ciphertext = read_ciphertext(options);
+    let cribs = read_cribs(options);
+    let keywords = read_keywords(options);
+    let max_period = options.parsed("max-period", 26);
+
+    let tableaux = known_plaintext::quagmire_tableaux(&keywords);
+    println!(
+        "Testing {} tableaux x 3 families for periods 1..={}",
+        tableaux.len(),
+        max_period
+    );
+
+    let recoveries = known_plaintext::recover_keys(&ciphertext, &cribs, &tableaux, max_period);
+    if recoveries.is_empty() {
+        println!("No family, tableau and period reproduces the cribs with a consistent key.");
+        return;
+    }
+
+    for recovery in &recoveries {
+        println!(
+            "\n{} / {} (plain {}, cipher {})",
+            recovery.family.name(),
+            recovery.tableau.name,
+            recovery.tableau.plain_alphabet(),
+            recovery.tableau.cipher_alphabet()
+        );
+        println!("  Crib keystream: {}", recovery.keystream_string());
+        for solution in &recovery.solutions {
+            println!(
+                "  Period {:>2}: key {} ({} agreeing letters)",
+                solution.period,
+                solution.key_string(&recovery.tableau),
+                solution.agreements
+            );
+        }
+    }
+}
+
+fn run_crib_drag(options: &Options) {
+    let ciphertext = read_ciphertext(options);
+    let cribs: Vec<String> = options
+        .value("crib")
+        .unwrap_or("BERLIN,CLOCK,EAST,NORTH,NORTHEAST")
+        .split(',')
+        .map(|c| c.trim().to_ascii_uppercase())
+        .filter(|c| !c.is_empty())
+        .collect();
+    let tableau: Tableau = options.parsed("tableau", Tableau::straight());
+    let families: Vec<CipherFamily> = match options.value("family") {
+        Some(_) => vec![options.parsed("family", CipherFamily::Vigenere)],
+        None => CipherFamily::ALL.to_vec(),
+    };
+
+    let min_word_length = options.parsed("min-word-length", 4);
+    let dictionary = match options.value("wordlist") {
+        Some(path) => {
+            let contents = fs::read_to_string(path).expect("Unable to read wordlist file");
+            Dictionary::new(contents.lines(), min_word_length)
+        }
+        None => Dictionary::new(scoring::common_words(), min_word_length),
+    };
+    let show_all = options.switch("all");
+
+    for crib in &cribs {
+        println!("\nCrib {} under {}:", crib, tableau.name);
+        let results = crib_drag::drag_crib(&ciphertext, crib, &families, &tableau, &dictionary);
+        for result in results.iter().filter(|r| show_all || r.is_flagged()) {
+            let mut flags = Vec::new();
+            if let Some(period) = result.period {
+                flags.push(format!("periodic ({})", period));
+            }
+            if let Some(word) = &result.dictionary_word {
+                flags.push(format!("word {}", word));
+            }
+            println!(
+                "  Offset {:>2} {:<16} key {} {}",
+                result.offset,
+                result.family.name(),
+                result.key_fragment,
+                flags.join(", ")
+            );
+        }
+    }
+}
+
+fn run_build_ngrams(options: &Options) {
+    let (Some(corpus_dir), Some(out_dir)) = (options.value("corpus"), options.value("out")) else {
+        eprintln!("build-ngrams requires --corpus <dir> and --out <dir>");
+        process::exit(2);
+    };
+    let max_n = options.parsed("max-n", ngram::MAX_NGRAM_LENGTH);
+    if max_n == 0 || max_n > ngram::MAX_NGRAM_LENGTH {
+        eprintln!("--max-n must be between 1 and {}", ngram::MAX_NGRAM_LENGTH);
+        process::exit(2);
+    }
+
+    let counts = corpus::count_corpus(Path::new(corpus_dir), max_n).expect("Unable to read corpus");
+    println!("Read {} files, {} letters", counts.files, counts.letters);
+
+    let out_dir = Path::new(out_dir);
+    fs::create_dir_all(out_dir).expect("Unable to create output directory");
+    for (i, ngram_counts) in counts.ngrams.iter().enumerate() {
+        let path = out_dir.join(corpus::ngram_file_name(i + 1));
+        corpus::write_counts(&path, ngram_counts).expect("Unable to write n-gram counts");
+        println!(
+            "Wrote {} distinct {}-grams to {}",
+            ngram_counts.len(),
+            i + 1,
+            path.display()
+        );
+    }
+
+    let path = out_dir.join(corpus::WORD_FILE_NAME);
+    corpus::write_counts(&path, &counts.words).expect("Unable to write word counts");
+    println!(
+        "Wrote {} distinct words to {}",
+        counts.words.len(),
+        path.display()
+    );
+
+    if let Some(monograms) = counts.ngrams.first() {
+        let mut letters: Vec<(&String, &u64)> = monograms.iter().collect();
+        letters.sort_by(|a, b| a.0.cmp(b.0));
+        println!("\nLetter frequencies:");
+        for (letter, count) in letters {
+            println!("  {}: {:.4}", letter, *count as f64 / counts.letters as f64);
+        }
+    }
+}
+
+fn run_segment(options: &Options) {
+    let text = read_text(options);
+    let model = read_word_model(options);
+
+    let segmentation = if options.switch("fuzzy") {
+        model.segment_fuzzy(&text)
+    } else {
+        model.segment(&text)
+    };
+    println!("Segmentation: {}", segmentation.to_spaced_string());
+    println!("Log probability: {:.4}", segmentation.log_probability);
+    println!(
+        "Known-word coverage: {:.4}",
+        segmentation.known_coverage(scoring::MIN_SCORED_WORD_LENGTH)
+    );
+    print_anomalies(&segmentation);
+}
+
+// Near-miss words and the stray letters they contain. In K1-K3 the
+// misspellings spelled out clues of their own.
+fn print_anomalies(segmentation: &Segmentation) {
+    let anomalies = segmentation.anomalies();
+    if anomalies.is_empty() {
+        return;
+    }
+    let described: Vec<String> = segmentation
+        .words
+        .iter()
+        .filter_map(|word| {
+            let m = word.fuzzy.as_ref()?;
+            let change = match m.anomaly {
+                Anomaly::Substituted { expected, found } => format!("{} for {}", found, expected),
+                Anomaly::Inserted { found } => format!("extra {}", found),
+                Anomaly::Deleted { expected } => format!("missing {}", expected),
+            };
+            Some(format!(
+                "{} ({}, {} at {})",
+                word.text, m.word, change, m.position
+            ))
+        })
+        .collect();
+    let letters: String = anomalies.iter().filter_map(|m| m.anomaly.found()).collect();
+    println!("    Anomalies: {}", described.join(", "));
+    if !letters.is_empty() {
+        println!("    Anomalous letters: {}", letters);
+    }
+}
+
+// The text to analyse: `--text` when given, otherwise the ciphertext.
+fn read_text(options: &Options) -> String {
+    match options.value("text") {
+        Some(text) => text.to_string(),
+        None => read_ciphertext(options),
+    }
+}
+
+// A fixed-width bar for eyeballing statistics in the terminal.
+fn bar(value: f64, scale: f64) -> String {
+    "#".repeat((value * scale).round().max(0.0) as usize)
+}
+
+fn run_stats(options: &Options) {
+    let text = read_text(options);
+    let max_period = options.parsed("max-period", 30);
+
+    println!("Letters: {}", statistics::letters(&text).len());
+    println!(
+        "IoC: {:.5} (English {:.4}, random {:.4})",
+        statistics::index_of_coincidence(&text),
+        statistics::ENGLISH_IOC,
+        statistics::RANDOM_IOC
+    );
+    match statistics::friedman_key_length(&text) {
+        Some(length) => println!("Friedman key length estimate: {:.2}", length),
+        None => println!("Friedman key length estimate: none (IoC at or below random)"),
+    }
+
+    println!("\nPeriodic IoC:");
+    for (period, ioc) in statistics::periodic_ioc_table(&text, max_period) {
+        println!("  {:>2}: {:.5} {}", period, ioc, bar(ioc, 400.0));
+    }
+
+    println!("\nKappa (self-coincidence by offset):");
+    for (offset, kappa) in statistics::kappa_table(&text, max_period) {
+        println!("  {:>2}: {:.5} {}", offset, kappa, bar(kappa, 400.0));
+    }
+}
+
+fn run_kasiski(options: &Options) {
+    let text = read_text(options);
+    let min_length = options.parsed("min-length", 3);
+    let max_period = options.parsed("max-period", 30);
+    let report = kasiski::kasiski(&text, min_length, max_period);
+
+    if report.repeats.is_empty() {
+        println!("No repeated sequences of {} or more letters.", min_length);
+        return;
+    }
+
+    println!("Repeated sequences:");
+    for repeat in &report.repeats {
+        println!(
+            "  {:<12} at {:?} spacings {:?} gcd {}",
+            repeat.sequence, repeat.positions, repeat.spacings, repeat.gcd
+        );
+    }
+
+    println!("\nLikely periods:");
+    for evidence in &report.periods {
+        println!(
+            "  {:>2}: {} spacing(s), weight {}",
+            evidence.period, evidence.spacings, evidence.weight
+        );
+    }
+}
+
+// Ranks cipher types for the ciphertext and for each `--text`, separated by
+// ';', such as the output of a candidate first layer.
+fn run_identify(options: &Options) {
+    let mut texts = vec![("ciphertext".to_string(), read_ciphertext(options))];
+    if let Some(extra) = options.value("text") {
+        for (i, text) in extra
+            .split(';')
+            .filter(|t| !t.trim().is_empty())
+            .enumerate()
+        {
+            texts.push((format!("text {}", i + 1), text.trim().to_string()));
+        }
+    }
+    let reference = match options.value("corpus") {
+        Some(dir) => corpus::corpus_letters(Path::new(dir)).expect("Unable to read corpus"),
+        None => corpus::KRYPTOS_PLAINTEXT.to_string(),
+    };
+    let samples = options.parsed("samples", 200);
+    let seed = read_seed(options);
+
+    for (label, text) in &texts {
+        let length = statistics::letters(text).len();
+        let identifier = Identifier::new(&reference, length, samples, seed).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(2);
+        });
+
+        let features = identifier.features(text);
+        println!(
+            "{} ({} letters, profiles from seed {})",
+            label, length, seed
+        );
+        for (name, value) in cipher_id::FEATURE_NAMES.iter().zip(features.values()) {
+            println!("  {:<6} {:.4}", name, value);
+        }
+        println!("  Ranking (distance to profile):");
+        for (rank, identification) in identifier.identify(text).iter().enumerate() {
+            println!(
+                "  {:>2}. {:<17} {:.3}",
+                rank + 1,
+                identification.cipher.name(),
+                identification.distance
+            );
+        }
+        println!();
+    }
+}
+
+fn run_slice(options: &Options) {
+    let ciphertext = read_ciphertext(options);
+    let family = options.parsed("family", CipherFamily::Vigenere);
+    let tableau: Tableau = options.parsed("tableau", Tableau::straight());
+    let reference = read_reference(options);
+    let scorer = read_scorer(options);
+
+    let solutions = match options.value("period") {
+        Some(_) => vec![slicing::solve_period(
+            &ciphertext,
+            options.parsed("period", 1),
+            family,
+            &tableau,
+            &reference,
+        )],
+        None => slicing::solve_periods(
+            &ciphertext,
+            options.parsed("max-period", 20),
+            family,
+            &tableau,
+            &reference,
+        ),
+    };
+
+    println!(
+        "Solving {} / {} column by column (chi-squared per column, {} score)",
+        family.name(),
+        tableau.name,
+        scorer.name()
+    );
+    for solution in &solutions {
+        println!(
+            "  Period {:>2}: key {:<20} chi2 {:>8.2} score {:>10.4}  {}",
+            solution.period,
+            solution.key_string(&tableau),
+            solution.mean_chi_squared(),
+            scorer.score(&solution.plaintext),
+            solution.plaintext
+        );
+    }
+}
+
+fn run_languages(options: &Options) {
+    let text = read_text(options);
+    let languages = read_languages(options);
+
+    println!("Language fit (higher is better):");
+    for fit in language::rank_languages(&text, &languages) {
+        println!(
+            "  {:<10} fit {:>8.4} ({})  word coverage {:.3}",
+            fit.language, fit.fit, fit.model, fit.word_coverage
+        );
+    }
+    if languages.iter().any(|l| l.ngram_model.is_none()) {
+        println!(
+            "Bundled languages are fitted on letter frequencies only; load name:dir models from build-ngrams for n-gram fits."
+        );
+    }
+}
+
+fn print_baseline(baseline: &Baseline, seed: u64) {
+    println!(
+        "Baseline over {} random texts (seed {}): mean {:.6}, std dev {:.6}, 95th percentile {:.6}, max {:.6}",
+        baseline.samples(),
+        seed,
+        baseline.mean,
+        baseline.std_dev,
+        baseline.quantile(0.95),
+        baseline.quantile(1.0)
+    );
+}
 
+fn run_calibrate(options: &Options) {
+    let ciphertext = read_ciphertext(options);
+    let scorer = read_scorer(options);
+    let alphabet = options.value("alphabet");
+    if alphabet.is_some_and(|alphabet| !alphabet.chars().any(|c| c.is_ascii_alphabetic())) {
+        eprintln!("--alphabet needs at least one letter to draw random keys from");
+        process::exit(2);
+    }
+    let config = CalibrationConfig {
+        samples: options.parsed("samples", 10_000),
+        random_key: alphabet
+            .map(|alphabet| (alphabet.to_string(), options.parsed("keyword-length", 11))),
+        seed: read_seed(options),
+    };
+
+    println!("Scorer: {}", scorer.name());
+    let baseline = significance::calibrate(&ciphertext, &scorer, &config);
+    print_baseline(&baseline, config.seed);
+
+    if let Some(text) = options.value("text") {
+        let score = scorer.score(text);
+        let significance = baseline.significance(score);
+        println!(
+            "Score: {:.8}, z: {:.3}, p: {:.3e}, empirical p: {:.3e}",
+            score, significance.z_score, significance.p_value, significance.empirical_p_value
+        );
+    }
+}
+
+// The alphabets the sweep tries: each keyword's substitution into the 15th
+// alphabet. `tested` rebuilds a stored sweep's alphabets the same way.
+fn sweep_alphabets(keywords: &[String]) -> Vec<String> {
     let substitution_techniques = vec![
         substitution::polyalphabetic_substitution,
         // Add more substitution techniques here
     ];
 
     // Define the 15th alphabet and its reverse
-    let alphabet_15 = "KRYPTOSABCDEFGHIJLNGHIJLMNQUVWXZ";
-
-    let base_alphabets = vec![
-        alphabet_15.to_string(),
-    ];
-
-    // Generate substitution keys from the wordlist
-    let wordlist_file = "/usr/share/dict/words";
-    let keyword_length = 11;
-    let substitution_keys = generate_keywords_from_wordlist(wordlist_file, keyword_length);
+    let base_alphabets = vec![SWEEP_ALPHABET.to_string()];
 
     let mut poly_alphabets: Vec<String> = Vec::new();
-
     for base_alphabet in &base_alphabets {
         for &substitution_fn in &substitution_techniques {
-            for substitution_key in &substitution_keys {
+            for substitution_key in keywords {
                 let substituted_alphabet =
                     substitution_fn(base_alphabet, substitution_key, base_alphabet);
                 poly_alphabets.push(substituted_alphabet);
             }
         }
     }
+    poly_alphabets
+}
+
+fn run_sweep(options: &Options, record: &mut RunRecord) {
+    let ciphertext = read_ciphertext(options);
+
+    // Generate substitution keys from the wordlist
+    let wordlist_file = options.value("wordlist").unwrap_or("/usr/share/dict/words");
+    let keyword_length = options.parsed("keyword-length", 11);
+    let substitution_keys = generate_keywords_from_wordlist(wordlist_file, keyword_length);
+    let poly_alphabets = sweep_alphabets(&substitution_keys);
+    record.set("wordlist", wordlist_file);
+    record.set("wordlist-digest", checkpoint::path_digest(wordlist_file));
+    record.set("keyword-length", keyword_length);
+    record.set(
+        "ciphertext-digest",
+        checkpoint::digest(ciphertext.as_bytes()),
+    );
+
+    let removal_patterns = read_removal_patterns(options, ciphertext.len());
+    println!("Removal hypotheses: {}", removal_patterns.len());
 
-    let total_iterations = poly_alphabets.len() * substitution_keys.len();
+    let total_iterations = removal_patterns.len() * poly_alphabets.len() * substitution_keys.len();
     let progress_interval = if total_iterations > 0 {
         total_iterations / 100
     } else {
         1
     }; // Print progress every 1% of total iterations
 
-    let progress_lock = Arc::new(Mutex::new(0));
+    let progress_lock = Arc::new(Mutex::new(0usize));
+
+    // With --enforce-cribs, keys whose output would not show the cribs at
+    // their published positions are rejected before decryption.
+    let enforce_cribs = options.switch("enforce-cribs");
+    let mut scorer_resources = read_scorer_resources(options);
+    let scorer = build_scorer(options, &scorer_resources);
+    println!("Scorer: {}", scorer.name());
+    let cribs = read_cribs(options);
+    // The crib scorer checks the cribs where they fall once a hypothesis'
+    // letters are stripped.
+    let mut scorer_for = |removal: Option<&RemovalPattern>| {
+        scorer_resources.cribs = match removal {
+            Some(pattern) => pattern.map_cribs(&cribs),
+            None => cribs.clone(),
+        };
+        build_scorer(options, &scorer_resources)
+    };
+    let rejected = AtomicUsize::new(0);
 
     let pool = rayon::ThreadPoolBuilder::new()
         .num_threads(8)
@@ -63,70 +1222,614 @@ fn main() {
     // Shared structure to collect results from all threads
     let top_poly_candidates = Arc::new(Mutex::new(BinaryHeap::new()));
 
-    pool.install(|| {
+    // With --checkpoint <file>, the position, counters and top candidates are
+    // saved every --checkpoint-interval seconds, and --resume picks up from
+    // the last save. The candidate order is total, so a resumed sweep ends
+    // with the same top list as one that ran straight through.
+    let checkpoint_path = options.value("checkpoint");
+    let checkpoint_interval = Duration::from_secs(options.parsed("checkpoint-interval", 300));
+    let removals: Vec<String> = removal_patterns
+        .iter()
+        .map(|removal| {
+            removal
+                .as_ref()
+                .map_or_else(|| "none".to_string(), |pattern| pattern.to_string())
+        })
+        .collect();
+    let crib_positions: Vec<String> = cribs
+        .iter()
+        .map(|crib| format!("{}@{}", crib.text, crib.position))
+        .collect();
+    let fingerprint = format!(
+        "ciphertext={} wordlist={}:{} keyword-length={} alphabets={} removals={}:{} enforce-cribs={} cribs={} scorer={} {}",
+        checkpoint::digest(ciphertext.as_bytes()),
+        wordlist_file,
+        checkpoint::path_digest(wordlist_file),
+        keyword_length,
+        poly_alphabets.len(),
+        removals.len(),
+        checkpoint::digest(removals.join(";").as_bytes()),
+        enforce_cribs,
+        crib_positions.join(","),
+        scorer.name(),
+        scorer_inputs(options)
+    );
+    // With --time-limit SECS, the sweep saves a checkpoint and stops at the
+    // first chunk boundary after SECS, to be continued with --resume.
+    let time_limit = options.value("time-limit").map(|_| {
+        if checkpoint_path.is_none() {
+            eprintln!("--time-limit needs --checkpoint <file>");
+            process::exit(2);
+        }
+        Duration::from_secs(options.parsed("time-limit", 0))
+    });
+    let started = Instant::now();
+    let mut start = (0, 0);
+    if options.switch("resume") {
+        let Some(path) = checkpoint_path else {
+            eprintln!("--resume needs --checkpoint <file>");
+            process::exit(2);
+        };
+        let checkpoint = SweepCheckpoint::load(path).unwrap_or_else(|e| {
+            eprintln!("Unable to load checkpoint {}: {}", path, e);
+            process::exit(2);
+        });
+        if checkpoint.fingerprint != fingerprint {
+            eprintln!("Checkpoint {} was written by a different sweep:", path);
+            let current: Vec<&str> = fingerprint.split(' ').collect();
+            for part in checkpoint.fingerprint.split(' ') {
+                if !current.contains(&part) {
+                    eprintln!("  checkpoint has {}", part);
+                }
+            }
+            process::exit(2);
+        }
+        println!(
+            "Resuming at removal hypothesis {}, alphabet {} with {} candidates",
+            checkpoint.removal,
+            checkpoint.alphabet,
+            checkpoint.candidates.len()
+        );
+        start = (checkpoint.removal, checkpoint.alphabet);
+        *progress_lock.lock().unwrap() = checkpoint.progress;
+        rejected.store(checkpoint.rejected, Ordering::Relaxed);
+        top_poly_candidates
+            .lock()
+            .unwrap()
+            .extend(checkpoint.candidates.into_iter().map(Reverse));
+    }
+    let save_checkpoint = |removal: usize, alphabet: usize| {
+        let Some(path) = checkpoint_path else {
+            return;
+        };
+        let mut candidates: Vec<Candidate> = top_poly_candidates
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|Reverse(candidate)| candidate.clone())
+            .collect();
+        candidates.sort_by(|a, b| b.cmp(a));
+        let checkpoint = SweepCheckpoint {
+            fingerprint: fingerprint.clone(),
+            removal,
+            alphabet,
+            progress: *progress_lock.lock().unwrap(),
+            rejected: rejected.load(Ordering::Relaxed),
+            candidates,
+        };
+        if let Err(e) = checkpoint.save(path) {
+            eprintln!("Unable to write checkpoint {}: {}", path, e);
+        }
+    };
+    let mut last_save = Instant::now();
 
-        poly_alphabets
-            .par_iter()
-            .map(|s| s.as_str())
-            .enumerate()
-            .for_each(|(k, alphabet)| {
-                let local_poly_heap = Mutex::new(BinaryHeap::new());
-
-                substitution_keys.par_iter().for_each(|substitution_key| {
-                    let plaintext = substitution::polyalphabetic_substitution(
-                        &ciphertext,
-                        substitution_key,
-                        alphabet,
-                    );
-                    let score = scoring::score_text(&plaintext);
-                    let candidate = Candidate::new(
-                        score,
-                        plaintext,
-                        0,
-                        1,
-                        k,
-                        substitution_key.to_string(),
-                        alphabet.to_string(),
-                    );
+    for (r, removal) in removal_patterns.iter().enumerate().skip(start.0) {
+        let crib_filter = CribFilter::with_removal(&ciphertext, &cribs, removal.as_ref());
+        let scorer = scorer_for(removal.as_ref());
+        let ciphertext = match removal {
+            Some(pattern) => pattern.apply(&ciphertext),
+            None => ciphertext.clone(),
+        };
 
-                    let mut heap = local_poly_heap
-                        .lock()
-                        .unwrap_or_else(|poisoned| poisoned.into_inner());
-                    heap.push(Reverse(candidate));
-                    if heap.len() > 250 {
-                        heap.pop();
-                    }
-                    // Update progress
-                    let mut progress = progress_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
-                    *progress += 1;
-                    if *progress % progress_interval == 0 {
-                        let default_candidate = Candidate::new(0.0, String::new(), 0, 0, 0, String::new(), String::new());
-                        let default_reverse = Reverse(default_candidate);
-                        let current_best = heap.peek().unwrap_or(&default_reverse);
-                        println!("Progress: {}%, Best Score: {:.8}, Keyword: {}, Plaintext: '{}'", *progress * 100 / total_iterations, current_best.0.score, current_best.0.keyword, current_best.0.plaintext);
-                    }
-                });
-
-                let local_poly_heap = local_poly_heap.into_inner().unwrap();
-                let mut global_poly_heap = top_poly_candidates.lock().unwrap();
-                for candidate in local_poly_heap {
-                    global_poly_heap.push(candidate);
-                    if global_poly_heap.len() > 250 {
-                        global_poly_heap.pop();
-                    }
-                }
+        // Alphabets go through in chunks so there is a consistent point to
+        // checkpoint between them.
+        let mut next = if r == start.0 { start.1 } else { 0 };
+        while next < poly_alphabets.len() {
+            let end = (next + SWEEP_CHUNK).min(poly_alphabets.len());
+            pool.install(|| {
+                poly_alphabets[next..end]
+                    .par_iter()
+                    .map(|s| s.as_str())
+                    .enumerate()
+                    .map(|(i, alphabet)| (next + i, alphabet))
+                    .for_each(|(k, alphabet)| {
+                        let constraint = if enforce_cribs {
+                            let Some(constraint) = crib_filter.for_polyalphabetic(alphabet) else {
+                                rejected.fetch_add(substitution_keys.len(), Ordering::Relaxed);
+                                *progress_lock
+                                    .lock()
+                                    .unwrap_or_else(|poisoned| poisoned.into_inner()) +=
+                                    substitution_keys.len();
+                                return;
+                            };
+                            Some(constraint)
+                        } else {
+                            None
+                        };
+
+                        let local_poly_heap = Mutex::new(BinaryHeap::new());
+
+                        substitution_keys.par_iter().for_each(|substitution_key| {
+                            if constraint
+                                .as_ref()
+                                .is_some_and(|constraint| !constraint.admits(substitution_key))
+                            {
+                                rejected.fetch_add(1, Ordering::Relaxed);
+                                *progress_lock
+                                    .lock()
+                                    .unwrap_or_else(|poisoned| poisoned.into_inner()) += 1;
+                                return;
+                            }
+
+                            let plaintext = substitution::polyalphabetic_substitution(
+                                &ciphertext,
+                                substitution_key,
+                                alphabet,
+                            );
+                            let score = scorer.score(&plaintext);
+                            let mut candidate = Candidate::new(
+                                score,
+                                plaintext,
+                                0,
+                                1,
+                                k,
+                                substitution_key.to_string(),
+                                alphabet.to_string(),
+                            );
+                            candidate.removal = removal.clone();
+
+                            let mut heap = local_poly_heap
+                                .lock()
+                                .unwrap_or_else(|poisoned| poisoned.into_inner());
+                            heap.push(Reverse(candidate));
+                            if heap.len() > 250 {
+                                heap.pop();
+                            }
+                            // Update progress
+                            let mut progress = progress_lock
+                                .lock()
+                                .unwrap_or_else(|poisoned| poisoned.into_inner());
+                            *progress += 1;
+                            if (*progress).is_multiple_of(progress_interval) {
+                                let default_candidate = Candidate::new(
+                                    0.0,
+                                    String::new(),
+                                    0,
+                                    0,
+                                    0,
+                                    String::new(),
+                                    String::new(),
+                                );
+                                let default_reverse = Reverse(default_candidate);
+                                let current_best = heap.peek().unwrap_or(&default_reverse);
+                                println!(
+                                "Progress: {}%, Best Score: {:.8}, Keyword: {}, Plaintext: '{}'",
+                                *progress * 100 / total_iterations,
+                                current_best.0.score,
+                                current_best.0.keyword,
+                                current_best.0.plaintext
+                            );
+                            }
+                        });
+
+                        let local_poly_heap = local_poly_heap.into_inner().unwrap();
+                        let mut global_poly_heap = top_poly_candidates.lock().unwrap();
+                        for candidate in local_poly_heap {
+                            global_poly_heap.push(candidate);
+                            if global_poly_heap.len() > 250 {
+                                global_poly_heap.pop();
+                            }
+                        }
+                    });
             });
-    });
+            next = end;
+            if time_limit.is_some_and(|limit| started.elapsed() >= limit) {
+                save_checkpoint(r, next);
+                println!("Stopped at the time limit; rerun with --resume to continue.");
+                // Only the run that finishes the sweep is stored.
+                process::exit(0);
+            }
+            if last_save.elapsed() >= checkpoint_interval {
+                save_checkpoint(r, next);
+                last_save = Instant::now();
+            }
+        }
+    }
+    save_checkpoint(removal_patterns.len(), 0);
+    if enforce_cribs {
+        println!(
+            "Rejected {} of {} key/alphabet combinations on the cribs",
+            rejected.load(Ordering::Relaxed),
+            total_iterations
+        );
+    }
     println!("\nTop Polyalphabetic Candidates:");
-    let top_poly_candidates = top_poly_candidates.lock().unwrap();
-    for Reverse(candidate) in top_poly_candidates.iter() {
+    let mut top_poly_candidates: Vec<Candidate> = top_poly_candidates
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|Reverse(candidate)| candidate.clone())
+        .collect();
+    top_poly_candidates.sort_by(|a, b| b.cmp(a));
+
+    for candidate in top_poly_candidates.iter_mut() {
+        let scorer = scorer_for(candidate.removal.as_ref());
+        candidate.breakdown = Some(scorer.explain(&candidate.plaintext));
+    }
+
+    // With --calibrate N, compare each score against N shuffles and random-key
+    // decryptions of the ciphertext scored the same way. Each removal
+    // hypothesis in the final list gets its own baseline, from the stripped
+    // ciphertext and the scorer with the cribs moved to match.
+    if let Some(samples) = options.value("calibrate") {
+        let config = CalibrationConfig {
+            samples: samples.parse().unwrap_or_else(|e| {
+                eprintln!("Invalid --calibrate: {}", e);
+                process::exit(2);
+            }),
+            random_key: Some((SWEEP_ALPHABET.to_string(), keyword_length)),
+            seed: read_seed(options),
+        };
+        record.set("seed", config.seed);
+        let mut baselines: HashMap<Option<RemovalPattern>, Baseline> = HashMap::new();
+        for removal in &removal_patterns {
+            if baselines.contains_key(removal)
+                || !top_poly_candidates.iter().any(|c| c.removal == *removal)
+            {
+                continue;
+            }
+            let removed = match removal {
+                Some(pattern) => pattern.apply(&ciphertext),
+                None => ciphertext.clone(),
+            };
+            let baseline =
+                significance::calibrate(&removed, &scorer_for(removal.as_ref()), &config);
+            if removal_patterns.len() > 1 {
+                println!(
+                    "Removed: {}",
+                    removal
+                        .as_ref()
+                        .map_or_else(|| "none".to_string(), |pattern| pattern.to_string())
+                );
+            }
+            print_baseline(&baseline, config.seed);
+            baselines.insert(removal.clone(), baseline);
+        }
+        for candidate in top_poly_candidates.iter_mut() {
+            candidate.significance =
+                Some(baselines[&candidate.removal].significance(candidate.score));
+        }
+    }
+    let word_model = read_word_model(options);
+    // With --languages, report which language each final plaintext fits best.
+    if options.value("languages").is_some() {
+        let languages = read_languages(options);
+        for candidate in top_poly_candidates.iter_mut() {
+            candidate.language = language::best_language(&candidate.plaintext, &languages);
+        }
+    }
+    for candidate in &top_poly_candidates {
+        let removal = candidate
+            .removal
+            .as_ref()
+            .map_or_else(|| "none".to_string(), |pattern| pattern.to_string());
         println!(
-            "Score: {:.8}, Substitution: {}, Alphabet: {}, Keyword: {}, Plaintext: '{}'",
+            "Score: {:.8}, Substitution: {}, Alphabet: {}, Keyword: {}, Removed: {}, Plaintext: '{}'",
             candidate.score,
             candidate.substitution,
             candidate.alphabet,
             candidate.keyword,
+            removal,
+            candidate.plaintext
+        );
+        record.add(
+            candidate.score,
+            &candidate.keyword,
+            &candidate.alphabet,
+            &candidate.plaintext,
+        );
+        if let Some(significance) = &candidate.significance {
+            println!(
+                "    z: {:.3}, p: {:.3e}, empirical p: {:.3e}",
+                significance.z_score, significance.p_value, significance.empirical_p_value
+            );
+        }
+        if options.switch("anomalies") {
+            print_anomalies(&word_model.segment_fuzzy(&candidate.plaintext));
+        }
+        if let Some(fit) = &candidate.language {
+            println!(
+                "    Language: {} (fit {:.4} on {}, word coverage {:.3})",
+                fit.language, fit.fit, fit.model, fit.word_coverage
+            );
+        }
+        if let Some(breakdown) = &candidate.breakdown {
+            print!("{}", breakdown);
+        }
+    }
+}
+
+// The files behind the scorer, by content, so a checkpoint is not resumed
+// under a different n-gram model, word list, reference or language.
+fn scorer_inputs(options: &Options) -> String {
+    let mut inputs = Vec::new();
+    for name in ["ngrams", "words"] {
+        if let Some(path) = options.value(name) {
+            inputs.push(format!("{}={}", name, checkpoint::path_digest(path)));
+        }
+    }
+    match options.value("reference").unwrap_or("english") {
+        reference @ ("english" | "uniform") => inputs.push(format!("reference={}", reference)),
+        path => inputs.push(format!("reference={}", checkpoint::path_digest(path))),
+    }
+    for entry in options.value("languages").unwrap_or("english").split(',') {
+        match entry.split_once(':') {
+            Some((name, dir)) => inputs.push(format!(
+                "language={}:{}",
+                name,
+                checkpoint::path_digest(dir)
+            )),
+            None => inputs.push(format!("language={}", entry)),
+        }
+    }
+    inputs.join(" ")
+}
+
+// Saves a search's record to `--store <dir>` (default `results`) unless
+// `--no-store` is given. A search is stored even without candidates, such
+// as a sweep whose every key failed the cribs, since `tested` still needs
+// to know what it covered.
+fn store_run(options: &Options, record: &mut RunRecord) {
+    if !SEARCH_COMMANDS.contains(&record.command.as_str()) || options.switch("no-store") {
+        return;
+    }
+    let dir = options.value("store").unwrap_or("results");
+    match ResultStore::new(dir).save(record) {
+        Ok(id) => println!("Stored as run {} in {}", id, dir),
+        Err(e) => eprintln!("Unable to store the run in {}: {}", dir, e),
+    }
+}
+
+fn read_stored_runs(options: &Options) -> Vec<RunRecord> {
+    let dir = options.value("store").unwrap_or("results");
+    ResultStore::new(dir).runs().unwrap_or_else(|e| {
+        eprintln!("Unable to read stored runs from {}: {}", dir, e);
+        process::exit(2);
+    })
+}
+
+fn format_config(config: &[(String, String)]) -> String {
+    config
+        .iter()
+        .map(|(name, value)| {
+            if value.is_empty() {
+                format!("--{}", name)
+            } else {
+                format!("--{} {}", name, value)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Lists the stored runs, optionally only those of `--command`.
+fn run_runs(options: &Options) {
+    let runs = read_stored_runs(options);
+    let command = options.value("command");
+    let runs: Vec<&RunRecord> = runs
+        .iter()
+        .filter(|run| command.is_none_or(|command| run.command == command))
+        .collect();
+    if runs.is_empty() {
+        println!("No stored runs.");
+        return;
+    }
+
+    for run in runs {
+        let best = run
+            .best()
+            .map_or_else(|| "-".to_string(), |c| format!("{:.8}", c.score));
+        println!(
+            "Run {}: {}, started at unix time {}, {:.1}s, {} candidates, best {}",
+            run.id,
+            run.command,
+            run.started,
+            run.seconds,
+            run.candidates.len(),
+            best
+        );
+        println!("    {}", format_config(&run.config));
+    }
+}
+
+// Compares the top candidates of `--runs A,B`, matching candidates on key,
+// alphabet and plaintext, after listing the settings that differ.
+fn run_diff_runs(options: &Options) {
+    let ids: Vec<usize> = read_list(options, "runs", Vec::new());
+    let [first, second] = ids.as_slice() else {
+        eprintln!("diff-runs needs --runs A,B");
+        process::exit(2);
+    };
+    let runs = read_stored_runs(options);
+    let find_run = |id: &usize| {
+        runs.iter().find(|run| run.id == *id).unwrap_or_else(|| {
+            eprintln!("No stored run {}", id);
+            process::exit(2);
+        })
+    };
+    let (first, second) = (find_run(first), find_run(second));
+
+    println!(
+        "Run {} ({}) vs run {} ({})",
+        first.id, first.command, second.id, second.command
+    );
+    let mut names: Vec<&str> = first
+        .config
+        .iter()
+        .chain(&second.config)
+        .map(|(name, _)| name.as_str())
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    for name in names {
+        let (a, b) = (first.setting(name), second.setting(name));
+        if a != b {
+            println!(
+                "  --{}: {} -> {}",
+                name,
+                a.unwrap_or("(unset)"),
+                b.unwrap_or("(unset)")
+            );
+        }
+    }
+
+    let diff = store::diff(first, second);
+    println!("In both ({}):", diff.common.len());
+    for (candidate, score) in &diff.common {
+        println!(
+            "  Score: {:.8} -> {:.8}, Key: {}, Alphabet: {}, Plaintext: '{}'",
+            candidate.score, score, candidate.key, candidate.alphabet, candidate.plaintext
+        );
+    }
+    for (id, candidates) in [(first.id, &diff.only_first), (second.id, &diff.only_second)] {
+        println!("Only in run {} ({}):", id, candidates.len());
+        for candidate in candidates.iter() {
+            println!(
+                "  Score: {:.8}, Key: {}, Alphabet: {}, Plaintext: '{}'",
+                candidate.score, candidate.key, candidate.alphabet, candidate.plaintext
+            );
+        }
+    }
+}
+
+// How many of a stored sweep's key, alphabet and removal combinations that
+// involve the key and/or alphabet it decrypted, and how many `--enforce-cribs`
+// rejected without decrypting. The key space is rebuilt from the run's own
+// options the way `run_sweep` builds it, once the wordlist and ciphertext
+// are confirmed to be the ones it ran on.
+fn sweep_coverage(
+    run: &RunRecord,
+    key: Option<&str>,
+    alphabet: Option<&str>,
+) -> Result<(usize, usize), String> {
+    let options = Options::from_entries(&run.command, &run.config);
+    let wordlist = run.setting("wordlist").unwrap_or("/usr/share/dict/words");
+    let contents =
+        fs::read(wordlist).map_err(|e| format!("cannot read its wordlist {}: {}", wordlist, e))?;
+    if run.setting("wordlist-digest") != Some(checkpoint::digest(&contents).as_str()) {
+        return Err(format!(
+            "its wordlist {} has changed since the run",
+            wordlist
+        ));
+    }
+    let ciphertext_path = options.value("ciphertext").unwrap_or("k4_ciphertext.txt");
+    let ciphertext = fs::read_to_string(ciphertext_path)
+        .map_err(|e| format!("cannot read its ciphertext {}: {}", ciphertext_path, e))?;
+    let ciphertext = ciphertext.trim();
+    if run.setting("ciphertext-digest") != Some(checkpoint::digest(ciphertext.as_bytes()).as_str())
+    {
+        return Err(format!(
+            "its ciphertext {} has changed since the run",
+            ciphertext_path
+        ));
+    }
+
+    let keyword_length = options.parsed("keyword-length", 11);
+    let keywords: Vec<String> = io::BufReader::new(&contents[..])
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| key_generation::wordlist_keyword(&line, keyword_length))
+        .collect();
+    let alphabets = sweep_alphabets(&keywords);
+    let wanted = |wanted: Option<&str>, value: &str| {
+        wanted.is_none_or(|wanted| wanted.eq_ignore_ascii_case(value))
+    };
+    let keywords: Vec<&String> = keywords.iter().filter(|k| wanted(key, k)).collect();
+    let alphabets: Vec<&String> = alphabets.iter().filter(|a| wanted(alphabet, a)).collect();
+
+    let enforce_cribs = options.switch("enforce-cribs");
+    let cribs = read_cribs(&options);
+    let (mut tried, mut rejected) = (0, 0);
+    for removal in read_removal_patterns(&options, ciphertext.len()) {
+        let crib_filter = CribFilter::with_removal(ciphertext, &cribs, removal.as_ref());
+        for alphabet in &alphabets {
+            let constraint = crib_filter.for_polyalphabetic(alphabet);
+            for keyword in &keywords {
+                if !enforce_cribs || constraint.as_ref().is_some_and(|c| c.admits(keyword)) {
+                    tried += 1;
+                } else {
+                    rejected += 1;
+                }
+            }
+        }
+    }
+    Ok((tried, rejected))
+}
+
+// Looks `--key` and/or `--alphabet` up in the store: among the candidates of
+// every run, and in the keyword space of every stored sweep.
+fn run_tested(options: &Options) {
+    let key = options.value("key");
+    let alphabet = options.value("alphabet");
+    if key.is_none() && alphabet.is_none() {
+        eprintln!("tested needs --key and/or --alphabet");
+        process::exit(2);
+    }
+    let runs = read_stored_runs(options);
+
+    let matches = store::find(&runs, key, alphabet);
+    for (run, candidate) in &matches {
+        println!(
+            "Run {} ({}) kept it: Score: {:.8}, Key: {}, Alphabet: {}, Plaintext: '{}'",
+            run.id,
+            run.command,
+            candidate.score,
+            candidate.key,
+            candidate.alphabet,
             candidate.plaintext
         );
     }
+
+    let mut tried_anywhere = false;
+    for run in runs.iter().filter(|run| run.command == "sweep") {
+        match sweep_coverage(run, key, alphabet) {
+            Ok((0, 0)) => {}
+            Ok((tried, rejected)) => {
+                tried_anywhere |= tried > 0;
+                let words = format!(
+                    "the {}-letter words of {}",
+                    run.setting("keyword-length").unwrap_or("?"),
+                    run.setting("wordlist").unwrap_or("?")
+                );
+                if rejected == 0 {
+                    println!("Run {} (sweep) tried it with {}", run.id, words);
+                } else if tried == 0 {
+                    println!(
+                        "Run {} (sweep) rejected it on the cribs without decrypting, with {}",
+                        run.id, words
+                    );
+                } else {
+                    println!(
+                        "Run {} (sweep) tried it in {} key/alphabet/removal combinations and rejected it on the cribs in {}, with {}",
+                        run.id, tried, rejected, words
+                    );
+                }
+            }
+            Err(reason) => println!("Run {} (sweep): cannot check, {}", run.id, reason),
+        }
+    }
+
+    if matches.is_empty() && !tried_anywhere {
+        println!("Not tried in any of {} stored runs.", runs.len());
+    }
 }
@@ -0,0 +1,225 @@
+// ngram.rs
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+// Log10 n-gram probabilities over A-Z, for n from 1 to 5, stored densely by
+// the base-26 value of each n-gram. N-grams missing from the counts get a
+// floor probability of 0.01 / total so that a single unseen n-gram costs a
+// bounded penalty instead of sinking the whole text.
+#[derive(Debug, Clone)]
+pub struct NgramModel {
+    n: usize,
+    log_probs: Vec<f32>,
+    floor: f64,
+}
+
+pub const MAX_NGRAM_LENGTH: usize = 5;
+
+impl NgramModel {
+    pub fn from_counts<I, S>(n: usize, counts: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = (S, u64)>,
+        S: AsRef<str>,
+    {
+        if n == 0 || n > MAX_NGRAM_LENGTH {
+            return Err(format!(
+                "n-gram length {} is outside 1..={}",
+                n, MAX_NGRAM_LENGTH
+            ));
+        }
+
+        let mut raw_counts = vec![0u64; 26usize.pow(n as u32)];
+        let mut total = 0u64;
+        for (ngram, count) in counts {
+            let ngram = ngram.as_ref();
+            let index = ngram_index(ngram.as_bytes())
+                .filter(|_| ngram.len() == n)
+                .ok_or_else(|| format!("'{}' is not a {}-gram over A-Z", ngram, n))?;
+            raw_counts[index] += count;
+            total += count;
+        }
+        if total == 0 {
+            return Err("n-gram counts are empty".to_string());
+        }
+
+        let total = total as f64;
+        // Rounded through f32 so that floor entries in the table compare equal
+        // to the floor itself.
+        let floor = (0.01 / total).log10() as f32 as f64;
+        let log_probs = raw_counts
+            .into_iter()
+            .map(|count| {
+                if count == 0 {
+                    floor as f32
+                } else {
+                    (count as f64 / total).log10() as f32
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            n,
+            log_probs,
+            floor,
+        })
+    }
+
+    // Reads `NGRAM COUNT` lines, e.g. `TION 13168375`. The n-gram length is
+    // taken from the file and must be the same on every line.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut counts = Vec::new();
+
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let (Some(ngram), Some(count), None) = (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid_data(format!(
+                    "line {}: expected 'NGRAM COUNT', got '{}'",
+                    line_number + 1,
+                    line
+                )));
+            };
+            let count = count.parse::<u64>().map_err(|e| {
+                invalid_data(format!(
+                    "line {}: bad count '{}': {}",
+                    line_number + 1,
+                    count,
+                    e
+                ))
+            })?;
+            counts.push((ngram.to_ascii_uppercase(), count));
+        }
+
+        let n = counts.first().map_or(0, |(ngram, _)| ngram.len());
+        Self::from_counts(n, counts).map_err(invalid_data)
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    pub fn floor(&self) -> f64 {
+        self.floor
+    }
+
+    pub fn log_probability(&self, ngram: &[u8]) -> f64 {
+        match ngram_index(ngram) {
+            Some(index) if ngram.len() == self.n => self.log_probs[index] as f64,
+            _ => self.floor,
+        }
+    }
+
+    // Total log10 probability of every n-gram window in the text, ignoring
+    // anything outside A-Z.
+    pub fn score(&self, text: &str) -> f64 {
+        let letters = letters_only(text);
+        letters
+            .windows(self.n)
+            .map(|window| self.log_probability(window))
+            .sum()
+    }
+
+    // Mean log10 probability per n-gram, so texts of different lengths (for
+    // example after null removal) can be compared.
+    pub fn score_per_ngram(&self, text: &str) -> f64 {
+        let letters = letters_only(text);
+        let windows = letters.len().saturating_sub(self.n - 1);
+        if windows == 0 {
+            return self.floor;
+        }
+        self.score(text) / windows as f64
+    }
+}
+
+fn letters_only(text: &str) -> Vec<u8> {
+    text.bytes()
+        .map(|b| b.to_ascii_uppercase())
+        .filter(u8::is_ascii_uppercase)
+        .collect()
+}
+
+fn ngram_index(ngram: &[u8]) -> Option<usize> {
+    ngram.iter().try_fold(0usize, |index, &b| {
+        b.is_ascii_uppercase()
+            .then(|| index * 26 + (b - b'A') as usize)
+    })
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn model() -> NgramModel {
+        NgramModel::from_counts(2, [("TH", 60), ("HE", 30), ("IN", 10)]).unwrap()
+    }
+
+    #[test]
+    fn load_reads_counts_and_skips_comments() {
+        let path = std::env::temp_dir().join(format!("kryptos-ngram-{}.txt", std::process::id()));
+        fs::write(&path, "# bigrams\nth 60\n\nHE 30\nIN 10\n").unwrap();
+        let loaded = NgramModel::load(&path).unwrap();
+        assert_eq!(loaded.n(), 2);
+        assert_eq!(loaded.log_probability(b"TH"), 0.6f64.log10() as f32 as f64);
+        assert_eq!(
+            loaded.log_probability(b"HE"),
+            model().log_probability(b"HE")
+        );
+
+        fs::write(&path, "TH 60\nTHE 30\n").unwrap();
+        assert!(NgramModel::load(&path).is_err());
+        fs::write(&path, "TH sixty\n").unwrap();
+        assert!(NgramModel::load(&path).is_err());
+        fs::write(&path, "# nothing\n").unwrap();
+        assert!(NgramModel::load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unseen_ngrams_get_the_floor() {
+        let model = model();
+        assert_eq!(model.floor(), (0.01f64 / 100.0).log10() as f32 as f64);
+        assert_eq!(model.log_probability(b"QZ"), model.floor());
+        // Anything that is not an n-gram over A-Z is priced at the floor too.
+        assert_eq!(model.log_probability(b"THE"), model.floor());
+        assert_eq!(model.log_probability(b"t?"), model.floor());
+        assert!(NgramModel::from_counts(6, [("ABCDEF", 1)]).is_err());
+        assert!(NgramModel::from_counts(2, [("TH", 0)]).is_err());
+    }
+
+    #[test]
+    fn scores_windows_of_letters_only() {
+        let model = model();
+        let th = model.log_probability(b"TH");
+        let he = model.log_probability(b"HE");
+        assert_eq!(model.score("the"), th + he);
+        assert_eq!(model.score("T-H E!"), th + he);
+        assert_eq!(model.score_per_ngram("THE"), (th + he) / 2.0);
+        assert_eq!(
+            model.score_per_ngram("THEQ"),
+            (th + he + model.floor()) / 3.0
+        );
+    }
+
+    #[test]
+    fn text_shorter_than_n_scores_the_floor() {
+        let model = model();
+        for text in ["", "T", "1 2 3"] {
+            assert_eq!(model.score(text), 0.0);
+            assert_eq!(model.score_per_ngram(text), model.floor());
+        }
+    }
+}
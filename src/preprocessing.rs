@@ -0,0 +1,210 @@
+// preprocessing.rs
+
+use crate::cribs::Crib;
+use std::fmt;
+use std::str::FromStr;
+
+// Positions to strip from the ciphertext before decryption, for hypotheses
+// where K4 carries nulls or deliberately dropped letters.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RemovalPattern {
+    EveryNth { n: usize, offset: usize },
+    Indices(Vec<usize>),
+}
+
+impl RemovalPattern {
+    pub fn removes(&self, position: usize) -> bool {
+        match self {
+            RemovalPattern::EveryNth { n, offset } => {
+                position >= *offset && (position - offset).is_multiple_of(*n)
+            }
+            RemovalPattern::Indices(indices) => indices.contains(&position),
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        text.chars()
+            .enumerate()
+            .filter(|&(i, _)| !self.removes(i))
+            .map(|(_, c)| c)
+            .collect()
+    }
+
+    // Where a position of the original text ends up once the pattern has
+    // been applied, or None if the position itself is removed.
+    pub fn map_position(&self, position: usize) -> Option<usize> {
+        if self.removes(position) {
+            return None;
+        }
+        Some(position - (0..position).filter(|&i| self.removes(i)).count())
+    }
+
+    // The cribs as they fall once the pattern has been applied, matching
+    // `CribFilter::with_removal`: crib letters over removed positions drop
+    // out and the rest shift left.
+    pub fn map_cribs(&self, cribs: &[Crib]) -> Vec<Crib> {
+        let mut mapped: Vec<Crib> = Vec::new();
+        for crib in cribs {
+            let mut run: Option<Crib> = None;
+            for (position, c) in crib.letters() {
+                let Some(position) = self.map_position(position) else {
+                    continue;
+                };
+                match &mut run {
+                    Some(current) if current.end() == position => current.text.push(c),
+                    _ => {
+                        mapped.extend(run.take());
+                        run = Some(Crib::new(position, &c.to_string()));
+                    }
+                }
+            }
+            mapped.extend(run);
+        }
+        mapped
+    }
+}
+
+impl fmt::Display for RemovalPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemovalPattern::EveryNth { n, offset } => write!(f, "every:{}:{}", n, offset),
+            RemovalPattern::Indices(indices) => {
+                let indices: Vec<String> = indices.iter().map(|i| i.to_string()).collect();
+                write!(f, "at:{}", indices.join(","))
+            }
+        }
+    }
+}
+
+// Parses `every:N[:OFFSET]` or `at:I,J,K`, the same forms Display produces.
+impl FromStr for RemovalPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_index = |v: &str| {
+            v.trim()
+                .parse::<usize>()
+                .map_err(|e| format!("invalid number '{}' in removal pattern: {}", v, e))
+        };
+
+        if let Some(rest) = s.strip_prefix("every:") {
+            let mut parts = rest.split(':');
+            let n = parse_index(parts.next().unwrap_or(""))?;
+            let offset = match parts.next() {
+                Some(offset) => parse_index(offset)?,
+                None => 0,
+            };
+            if n < 2 || offset >= n {
+                return Err(format!(
+                    "every:{}:{} must have n >= 2 and offset < n",
+                    n, offset
+                ));
+            }
+            Ok(RemovalPattern::EveryNth { n, offset })
+        } else if let Some(rest) = s.strip_prefix("at:") {
+            let mut indices = rest
+                .split(',')
+                .map(parse_index)
+                .collect::<Result<Vec<_>, _>>()?;
+            indices.sort_unstable();
+            indices.dedup();
+            Ok(RemovalPattern::Indices(indices))
+        } else {
+            Err(format!(
+                "unknown removal pattern '{}', expected every:N[:OFFSET] or at:I,J,...",
+                s
+            ))
+        }
+    }
+}
+
+// Every "remove each nth letter" pattern for n in min_n..=max_n at every offset.
+pub fn enumerate_every_nth(min_n: usize, max_n: usize) -> Vec<RemovalPattern> {
+    (min_n.max(2)..=max_n)
+        .flat_map(|n| (0..n).map(move |offset| RemovalPattern::EveryNth { n, offset }))
+        .collect()
+}
+
+// Every way of removing exactly `count` letters from a text of the given length.
+pub fn enumerate_index_removals(length: usize, count: usize) -> Vec<RemovalPattern> {
+    let mut patterns = Vec::new();
+    if count == 0 || count > length {
+        return patterns;
+    }
+
+    let mut indices: Vec<usize> = (0..count).collect();
+    loop {
+        patterns.push(RemovalPattern::Indices(indices.clone()));
+
+        // Advance to the next combination in lexicographic order.
+        let Some(i) = (0..count).rev().find(|&i| indices[i] < length - count + i) else {
+            break;
+        };
+        indices[i] += 1;
+        for j in i + 1..count {
+            indices[j] = indices[j - 1] + 1;
+        }
+    }
+    patterns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_parse_and_display_round_trip() {
+        for spec in ["every:3:1", "at:2,5,9"] {
+            let pattern: RemovalPattern = spec.parse().unwrap();
+            assert_eq!(pattern.to_string(), spec);
+        }
+        assert_eq!(
+            "every:4".parse::<RemovalPattern>(),
+            Ok(RemovalPattern::EveryNth { n: 4, offset: 0 })
+        );
+        assert_eq!(
+            "at:9,2,2".parse::<RemovalPattern>(),
+            Ok(RemovalPattern::Indices(vec![2, 9]))
+        );
+        assert!("every:1".parse::<RemovalPattern>().is_err());
+        assert!("every:3:3".parse::<RemovalPattern>().is_err());
+        assert!("at:x".parse::<RemovalPattern>().is_err());
+        assert!("drop:3".parse::<RemovalPattern>().is_err());
+    }
+
+    #[test]
+    fn apply_and_map_position_agree() {
+        let text = "ABCDEFGHIJ";
+        let every = RemovalPattern::EveryNth { n: 3, offset: 1 };
+        assert_eq!(every.apply(text), "ACDFGIJ");
+        let indices = RemovalPattern::Indices(vec![0, 4]);
+        assert_eq!(indices.apply(text), "BCDFGHIJ");
+
+        for pattern in [every, indices] {
+            let stripped: Vec<char> = pattern.apply(text).chars().collect();
+            for (position, c) in text.chars().enumerate() {
+                match pattern.map_position(position) {
+                    Some(mapped) => assert_eq!(stripped[mapped], c),
+                    None => assert!(pattern.removes(position)),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cribs_close_up_over_removed_letters() {
+        let pattern = RemovalPattern::Indices(vec![1, 4]);
+        let mapped = pattern.map_cribs(&[Crib::new(2, "XYZW"), Crib::new(7, "Q")]);
+        assert_eq!(mapped, vec![Crib::new(1, "XYW"), Crib::new(5, "Q")]);
+    }
+
+    #[test]
+    fn index_removals_cover_every_combination() {
+        let patterns = enumerate_index_removals(5, 2);
+        assert_eq!(patterns.len(), 10);
+        assert_eq!(patterns[0], RemovalPattern::Indices(vec![0, 1]));
+        assert_eq!(patterns[9], RemovalPattern::Indices(vec![3, 4]));
+        assert!(enumerate_index_removals(2, 3).is_empty());
+        assert_eq!(enumerate_every_nth(2, 3).len(), 5);
+    }
+}
@@ -0,0 +1,380 @@
+// quagmire.rs
+
+use crate::alphabet_analysis::{self, KeywordDerivation};
+use crate::hill_climb::{KeyMove, KeySpace};
+use crate::scorer::Scorer;
+use crate::scoring;
+use crate::seeding;
+use crate::slicing;
+use crate::substitution::{self, CipherFamily, Tableau, STRAIGHT_ALPHABET};
+use rand::Rng;
+use rayon::prelude::*;
+use std::str::FromStr;
+
+// Where the unknown mixed alphabet sits in the tableau: the plaintext row
+// (Quagmire I), the ciphertext row (II) or both (III, as for K1 and K2).
+// Quagmire IV's two independent alphabets are out of reach of a climb over
+// one alphabet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quagmire {
+    One,
+    Two,
+    Three,
+}
+
+impl Quagmire {
+    pub fn tableau(&self, alphabet: &str) -> Result<Tableau, String> {
+        match self {
+            Quagmire::One => Tableau::new("quagmire1", alphabet, STRAIGHT_ALPHABET),
+            Quagmire::Two => Tableau::new("quagmire2", STRAIGHT_ALPHABET, alphabet),
+            Quagmire::Three => Tableau::new("quagmire3", alphabet, alphabet),
+        }
+    }
+}
+
+impl FromStr for Quagmire {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1" | "quagmire1" => Ok(Quagmire::One),
+            "2" | "quagmire2" => Ok(Quagmire::Two),
+            "3" | "quagmire3" => Ok(Quagmire::Three),
+            other => Err(format!("unknown quagmire type '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QuagmireSolver {
+    pub quagmire: Quagmire,
+    pub family: CipherFamily,
+    pub key_length: usize,
+    // Proposals per restart.
+    pub iterations: usize,
+    // A restart gives up after this many proposals in a row fail to improve.
+    pub patience: usize,
+    pub restarts: usize,
+    // Letter distribution the key columns are fitted to.
+    pub reference: [f64; 26],
+    // Longest keyword considered when deriving the alphabet from a keyword.
+    pub max_keyword_length: usize,
+    // Alphabets to climb from, such as those of candidate keywords. Each
+    // seed gets one climb before the random restarts.
+    pub seeds: Vec<String>,
+    // Each climb draws from its own stream of this seed.
+    pub seed: u64,
+}
+
+impl Default for QuagmireSolver {
+    fn default() -> Self {
+        Self {
+            quagmire: Quagmire::Three,
+            family: CipherFamily::Vigenere,
+            key_length: 8,
+            iterations: 100_000,
+            patience: 5_000,
+            restarts: 8,
+            reference: scoring::english_letter_frequencies(),
+            max_keyword_length: 12,
+            seeds: Vec::new(),
+            seed: seeding::random_seed(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QuagmireSolution {
+    pub alphabet: String,
+    // The key as shifts, which unlike key letters do not depend on how the
+    // alphabet is rotated.
+    pub shifts: Vec<usize>,
+    pub plaintext: String,
+    pub score: f64,
+    pub derivation: KeywordDerivation,
+}
+
+impl QuagmireSolution {
+    // The key letters as read from the recovered tableau.
+    pub fn key(&self, quagmire: Quagmire) -> String {
+        match quagmire.tableau(&self.alphabet) {
+            Ok(tableau) => self.shifts.iter().map(|&s| tableau.key_letter(s)).collect(),
+            Err(_) => String::new(),
+        }
+    }
+}
+
+// Recovers the mixed alphabet and the periodic key together. Each climb
+// starts from a seed alphabet or, for the restarts, a random one, and
+// swaps letters of the alphabet. Every proposed alphabet gets
+// its best key by fitting each column's shift to the reference letter
+// frequencies, and is then scored. The summit's key is finally polished
+// against the scorer itself. Returns each climb's summit, best first.
+//
+// From a random start the fitted keys are noise until most of the alphabet
+// is right, so the climbs rarely find a Quagmire III row unaided; a seed
+// within a few swaps of the row, such as a misspelt keyword, is enough.
+pub fn solve(
+    ciphertext: &str,
+    config: &QuagmireSolver,
+    scorer: &dyn Scorer,
+) -> Vec<QuagmireSolution> {
+    let alphabet_space = KeySpace::alphabet_permutation();
+    let starts: Vec<Option<Vec<char>>> = config
+        .seeds
+        .iter()
+        .filter(|seed| config.quagmire.tableau(seed).is_ok())
+        .map(|seed| Some(seed.chars().collect()))
+        .chain((0..config.restarts).map(|_| None))
+        .collect();
+
+    let mut solutions: Vec<QuagmireSolution> = starts
+        .into_par_iter()
+        .enumerate()
+        .map(|(index, start)| {
+            let mut rng = seeding::stream(config.seed, index);
+            let alphabet = start.unwrap_or_else(|| alphabet_space.random_key(&mut rng));
+            climb(ciphertext, config, alphabet, scorer, &mut rng)
+        })
+        .collect();
+    solutions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    solutions
+}
+
+// The key for another rotation of the alphabet, such as the keyword-derived
+// one: the shifts offset by whichever amount scores best.
+pub fn rekey(
+    ciphertext: &str,
+    config: &QuagmireSolver,
+    alphabet: &str,
+    shifts: &[usize],
+    scorer: &dyn Scorer,
+) -> Option<(String, String, f64)> {
+    let tableau = config.quagmire.tableau(alphabet).ok()?;
+    (0..26)
+        .map(|offset| {
+            let shifted: Vec<usize> = shifts.iter().map(|s| (s + offset) % 26).collect();
+            let plaintext = decrypt(ciphertext, &shifted, config.family, &tableau);
+            let key: String = shifted.iter().map(|&s| tableau.key_letter(s)).collect();
+            let score = scorer.score(&plaintext);
+            (key, plaintext, score)
+        })
+        .max_by(|a, b| a.2.total_cmp(&b.2))
+}
+
+fn climb<R: Rng>(
+    ciphertext: &str,
+    config: &QuagmireSolver,
+    mut alphabet: Vec<char>,
+    scorer: &dyn Scorer,
+    rng: &mut R,
+) -> QuagmireSolution {
+    let alphabet_space = KeySpace::alphabet_permutation();
+    let (mut shifts, mut score) = fit_key(ciphertext, config, &alphabet, scorer);
+
+    let mut stalled = 0;
+    for _ in 0..config.iterations {
+        if stalled >= config.patience {
+            break;
+        }
+        let mut proposal = alphabet.clone();
+        if rng.gen_bool(0.5) {
+            relocate(&mut proposal, rng);
+        } else if !alphabet_space.mutate(&mut proposal, KeyMove::Swap, rng) {
+            continue;
+        }
+
+        let (proposed_shifts, proposed_score) = fit_key(ciphertext, config, &proposal, scorer);
+        if proposed_score > score {
+            alphabet = proposal;
+            shifts = proposed_shifts;
+            score = proposed_score;
+            stalled = 0;
+        } else {
+            stalled += 1;
+        }
+    }
+    score = polish_shifts(ciphertext, config, &alphabet, &mut shifts, scorer);
+
+    let alphabet: String = alphabet.into_iter().collect();
+    let plaintext = match config.quagmire.tableau(&alphabet) {
+        Ok(tableau) => decrypt(ciphertext, &shifts, config.family, &tableau),
+        Err(_) => String::new(),
+    };
+    QuagmireSolution {
+        derivation: alphabet_analysis::keyword_derivation(&alphabet, config.max_keyword_length),
+        alphabet,
+        shifts,
+        plaintext,
+        score,
+    }
+}
+
+// Takes one letter out and puts it back elsewhere, shifting the letters in
+// between: what a misspelt keyword does to a keyed alphabet.
+fn relocate<R: Rng>(alphabet: &mut Vec<char>, rng: &mut R) {
+    let letter = alphabet.remove(rng.gen_range(0..alphabet.len()));
+    alphabet.insert(rng.gen_range(0..=alphabet.len()), letter);
+}
+
+// The column-by-column best key for an alphabet and the score it gives.
+fn fit_key(
+    ciphertext: &str,
+    config: &QuagmireSolver,
+    alphabet: &[char],
+    scorer: &dyn Scorer,
+) -> (Vec<usize>, f64) {
+    let alphabet: String = alphabet.iter().collect();
+    match config.quagmire.tableau(&alphabet) {
+        Ok(tableau) => {
+            let solution = slicing::solve_period(
+                ciphertext,
+                config.key_length,
+                config.family,
+                &tableau,
+                &config.reference,
+            );
+            (solution.shifts, scorer.score(&solution.plaintext))
+        }
+        Err(_) => (vec![0; config.key_length.max(1)], f64::NEG_INFINITY),
+    }
+}
+
+// Coordinate ascent over the key: each column in turn takes the shift that
+// scores best with the others held, until a full pass changes nothing.
+fn polish_shifts(
+    ciphertext: &str,
+    config: &QuagmireSolver,
+    alphabet: &[char],
+    shifts: &mut [usize],
+    scorer: &dyn Scorer,
+) -> f64 {
+    let mut score = evaluate(ciphertext, config, alphabet, shifts, scorer);
+    loop {
+        let mut changed = false;
+        for i in 0..shifts.len() {
+            let current = shifts[i];
+            for shift in (0..26).filter(|&s| s != current) {
+                let previous = shifts[i];
+                shifts[i] = shift;
+                let candidate = evaluate(ciphertext, config, alphabet, shifts, scorer);
+                if candidate > score {
+                    score = candidate;
+                    changed = true;
+                } else {
+                    shifts[i] = previous;
+                }
+            }
+        }
+        if !changed {
+            return score;
+        }
+    }
+}
+
+fn evaluate(
+    ciphertext: &str,
+    config: &QuagmireSolver,
+    alphabet: &[char],
+    shifts: &[usize],
+    scorer: &dyn Scorer,
+) -> f64 {
+    let alphabet: String = alphabet.iter().collect();
+    match config.quagmire.tableau(&alphabet) {
+        Ok(tableau) => scorer.score(&decrypt(ciphertext, shifts, config.family, &tableau)),
+        Err(_) => f64::NEG_INFINITY,
+    }
+}
+
+fn decrypt(ciphertext: &str, shifts: &[usize], family: CipherFamily, tableau: &Tableau) -> String {
+    let key: String = shifts.iter().map(|&s| tableau.key_letter(s)).collect();
+    substitution::periodic_decrypt(ciphertext, &key, family, tableau)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corpus;
+    use crate::key_generation::keyed_alphabet;
+    use crate::ngram::NgramModel;
+    use crate::scorer::{MonogramScorer, NgramScorer};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn trigram_scorer() -> NgramScorer {
+        let mut counts = HashMap::new();
+        corpus::count_ngrams(
+            &corpus::normalize(corpus::KRYPTOS_PLAINTEXT),
+            3,
+            &mut counts,
+        );
+        NgramScorer::new(Arc::new(NgramModel::from_counts(3, counts).unwrap()))
+    }
+
+    #[test]
+    fn solve_recovers_a_planted_alphabet_from_a_misspelt_seed() {
+        let plaintext = &corpus::normalize(corpus::KRYPTOS_PLAINTEXT)[..300];
+        let alphabet = keyed_alphabet("KRYPTOS");
+        let ciphertext = substitution::periodic_encrypt(
+            plaintext,
+            "LAYER",
+            CipherFamily::Vigenere,
+            &Quagmire::Three.tableau(&alphabet).unwrap(),
+        );
+        let config = QuagmireSolver {
+            key_length: 5,
+            iterations: 3_000,
+            patience: 1_000,
+            restarts: 1,
+            seeds: vec![keyed_alphabet("KRYPOTS")],
+            seed: 23,
+            ..QuagmireSolver::default()
+        };
+        let solutions = solve(&ciphertext, &config, &trigram_scorer());
+
+        assert_eq!(solutions.len(), 2);
+        let best = &solutions[0];
+        assert_eq!(best.plaintext, plaintext);
+        assert_eq!(best.alphabet, alphabet);
+        assert_eq!(best.key(Quagmire::Three), "LAYER");
+        assert_eq!(best.derivation.keyword, "KRYPTOS");
+        assert!(best.derivation.is_exact());
+    }
+
+    #[test]
+    fn rekey_finds_the_key_for_a_rotated_alphabet() {
+        let plaintext = &corpus::normalize(corpus::KRYPTOS_PLAINTEXT)[..120];
+        let alphabet = keyed_alphabet("KRYPTOS");
+        let tableau = Quagmire::Three.tableau(&alphabet).unwrap();
+        let ciphertext =
+            substitution::periodic_encrypt(plaintext, "LAYER", CipherFamily::Vigenere, &tableau);
+        let rotated: String = alphabet[3..].chars().chain(alphabet[..3].chars()).collect();
+        let config = QuagmireSolver {
+            key_length: 5,
+            ..QuagmireSolver::default()
+        };
+        let shifts: Vec<usize> = "LAYER".chars().map(|c| alphabet.find(c).unwrap()).collect();
+        let (key, decrypted, _) = rekey(
+            &ciphertext,
+            &config,
+            &rotated,
+            &shifts,
+            &MonogramScorer::english(),
+        )
+        .unwrap();
+        // The same shifts read as other letters against the rotated alphabet.
+        assert_eq!(decrypted, plaintext);
+        assert_ne!(key, "LAYER");
+        let rotated_tableau = Quagmire::Three.tableau(&rotated).unwrap();
+        assert_eq!(
+            substitution::periodic_decrypt(
+                &ciphertext,
+                &key,
+                CipherFamily::Vigenere,
+                &rotated_tableau
+            ),
+            plaintext
+        );
+        assert_eq!("3".parse::<Quagmire>(), Ok(Quagmire::Three));
+    }
+}
@@ -0,0 +1,830 @@
+// scorer.rs
+
+use crate::cribs::Crib;
+use crate::language::Language;
+use crate::ngram::NgramModel;
+use crate::scoring;
+use crate::segmentation::WordModel;
+use crate::statistics;
+use std::fmt;
+use std::sync::Arc;
+
+// A fitness function for candidate plaintexts. Higher is always better, so
+// statistics where smaller means closer to English are negated.
+pub trait Scorer: Send + Sync {
+    fn name(&self) -> String;
+    fn score(&self, text: &str) -> f64;
+
+    // The words or n-grams behind the score, where the scorer has any.
+    fn matches(&self, _text: &str) -> Vec<ScoreMatch> {
+        Vec::new()
+    }
+
+    // The score split into its components. Plain scorers are a single
+    // component of weight one; `LegacyScorer` and `WeightedScorer` report
+    // each of their parts.
+    fn explain(&self, text: &str) -> ScoreBreakdown {
+        let raw = self.score(text);
+        ScoreBreakdown {
+            total: raw,
+            components: vec![ComponentScore {
+                name: self.name(),
+                raw,
+                weight: 1.0,
+                contribution: raw,
+                matches: self.matches(text),
+            }],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreMatch {
+    pub text: String,
+    pub position: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentScore {
+    pub name: String,
+    pub raw: f64,
+    pub weight: f64,
+    pub contribution: f64,
+    pub matches: Vec<ScoreMatch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ScoreBreakdown {
+    pub total: f64,
+    pub components: Vec<ComponentScore>,
+}
+
+impl fmt::Display for ScoreBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for component in &self.components {
+            write!(
+                f,
+                "    {:<17} raw {:>12.6} x {:<6} = {:>12.6}",
+                component.name, component.raw, component.weight, component.contribution
+            )?;
+            if !component.matches.is_empty() {
+                let matches: Vec<String> = component
+                    .matches
+                    .iter()
+                    .map(|m| format!("{}@{}", m.text, m.position))
+                    .collect();
+                write!(f, "  [{}]", matches.join(" "))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// Known words of at least `scoring::MIN_SCORED_WORD_LENGTH` letters in the
+// best segmentation of the text.
+fn known_word_matches(model: &WordModel, text: &str) -> Vec<ScoreMatch> {
+    model
+        .segment(text)
+        .words
+        .into_iter()
+        .filter(|w| w.known && w.text.len() >= scoring::MIN_SCORED_WORD_LENGTH)
+        .map(|w| ScoreMatch {
+            text: w.text,
+            position: w.position,
+        })
+        .collect()
+}
+
+// The common words `scoring::score_text` finds in the text.
+fn legacy_word_matches(text: &str) -> Vec<ScoreMatch> {
+    scoring::matched_words(text)
+        .into_iter()
+        .map(|(position, word)| ScoreMatch {
+            text: word.to_string(),
+            position,
+        })
+        .collect()
+}
+
+// The original hand-weighted `scoring::score_text`.
+pub struct LegacyScorer;
+
+impl Scorer for LegacyScorer {
+    fn name(&self) -> String {
+        "legacy".to_string()
+    }
+
+    fn score(&self, text: &str) -> f64 {
+        scoring::score_text(text)
+    }
+
+    fn matches(&self, text: &str) -> Vec<ScoreMatch> {
+        let mut matches = legacy_word_matches(text);
+        matches.extend(
+            scoring::matched_trigrams(text)
+                .into_iter()
+                .map(|(position, trigram)| ScoreMatch {
+                    text: trigram.to_string(),
+                    position,
+                }),
+        );
+        matches
+    }
+
+    // Each hand-weighted part of the score, with the words and trigrams
+    // behind their parts.
+    fn explain(&self, text: &str) -> ScoreBreakdown {
+        let components: Vec<ComponentScore> = scoring::score_text_components(text)
+            .iter()
+            .map(|component| ComponentScore {
+                name: component.name.to_string(),
+                raw: component.raw,
+                weight: component.weight,
+                contribution: component.raw * component.weight,
+                matches: match component.name {
+                    "words" => legacy_word_matches(text),
+                    "trigrams" => scoring::matched_trigrams(text)
+                        .into_iter()
+                        .map(|(position, trigram)| ScoreMatch {
+                            text: trigram.to_string(),
+                            position,
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                },
+            })
+            .collect();
+
+        ScoreBreakdown {
+            total: components.iter().map(|c| c.contribution).sum(),
+            components,
+        }
+    }
+}
+
+// Mean log10 letter probability under the English letter frequencies.
+pub struct MonogramScorer {
+    log_frequencies: [f64; 26],
+}
+
+impl MonogramScorer {
+    pub fn english() -> Self {
+        Self::new(&scoring::english_letter_frequencies())
+    }
+
+    pub fn new(frequencies: &[f64; 26]) -> Self {
+        let total: f64 = frequencies.iter().sum();
+        let mut log_frequencies = [0.0; 26];
+        for (log_frequency, &frequency) in log_frequencies.iter_mut().zip(frequencies) {
+            *log_frequency = (frequency.max(1e-6) / total).log10();
+        }
+        Self { log_frequencies }
+    }
+}
+
+impl Scorer for MonogramScorer {
+    fn name(&self) -> String {
+        "monogram".to_string()
+    }
+
+    fn score(&self, text: &str) -> f64 {
+        let counts = statistics::letter_counts(text);
+        let total: usize = counts.iter().sum();
+        if total == 0 {
+            return f64::NEG_INFINITY;
+        }
+        let sum: f64 = counts
+            .iter()
+            .zip(&self.log_frequencies)
+            .map(|(&count, &log_frequency)| count as f64 * log_frequency)
+            .sum();
+        sum / total as f64
+    }
+}
+
+// Mean log10 n-gram probability from a loaded model.
+pub struct NgramScorer {
+    model: Arc<NgramModel>,
+}
+
+impl NgramScorer {
+    pub fn new(model: Arc<NgramModel>) -> Self {
+        Self { model }
+    }
+}
+
+impl Scorer for NgramScorer {
+    fn name(&self) -> String {
+        format!("ngram{}", self.model.n())
+    }
+
+    fn score(&self, text: &str) -> f64 {
+        self.model.score_per_ngram(text)
+    }
+
+    // The ten most probable n-grams in the text.
+    fn matches(&self, text: &str) -> Vec<ScoreMatch> {
+        let letters: Vec<u8> = text
+            .bytes()
+            .map(|b| b.to_ascii_uppercase())
+            .filter(u8::is_ascii_uppercase)
+            .collect();
+        let mut windows: Vec<(usize, f64)> = letters
+            .windows(self.model.n())
+            .enumerate()
+            .map(|(i, window)| (i, self.model.log_probability(window)))
+            .filter(|&(_, log_probability)| log_probability > self.model.floor())
+            .collect();
+        windows.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        windows
+            .into_iter()
+            .take(10)
+            .map(|(position, _)| ScoreMatch {
+                text: String::from_utf8_lossy(&letters[position..position + self.model.n()])
+                    .into_owned(),
+                position,
+            })
+            .collect()
+    }
+}
+
+// Closeness of the index of coincidence to a target, 0.0667 for English.
+pub struct IocScorer {
+    target: f64,
+}
+
+impl IocScorer {
+    pub const ENGLISH: f64 = statistics::ENGLISH_IOC;
+
+    pub fn new(target: f64) -> Self {
+        Self { target }
+    }
+}
+
+impl Scorer for IocScorer {
+    fn name(&self) -> String {
+        "ioc".to_string()
+    }
+
+    fn score(&self, text: &str) -> f64 {
+        -(statistics::index_of_coincidence(text) - self.target).abs()
+    }
+}
+
+// Negated distance from the target IoC of the best-fitting column split
+// for periods up to max_period. Undoing a transposition that sits on top
+// of a periodic substitution brings the columns back, so this rates
+// transposition keys before the periodic key is known.
+pub struct PeriodicIocScorer {
+    target: f64,
+    max_period: usize,
+}
+
+impl PeriodicIocScorer {
+    pub const DEFAULT_MAX_PERIOD: usize = 12;
+
+    pub fn new(target: f64, max_period: usize) -> Self {
+        Self {
+            target,
+            max_period: max_period.max(1),
+        }
+    }
+}
+
+impl Scorer for PeriodicIocScorer {
+    fn name(&self) -> String {
+        "periodic-ioc".to_string()
+    }
+
+    fn score(&self, text: &str) -> f64 {
+        statistics::periodic_ioc_table(text, self.max_period)
+            .into_iter()
+            .map(|(_, ioc)| -(ioc - self.target).abs())
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+}
+
+// The digraph IoC, which rates transposition keys on top of a
+// monoalphabetic substitution.
+pub struct DigraphIocScorer;
+
+impl Scorer for DigraphIocScorer {
+    fn name(&self) -> String {
+        "dic".to_string()
+    }
+
+    fn score(&self, text: &str) -> f64 {
+        statistics::digraph_ioc(text)
+    }
+}
+
+// Negated chi-squared distance from a reference letter distribution.
+pub struct ChiSquaredScorer {
+    expected: [f64; 26],
+}
+
+impl ChiSquaredScorer {
+    pub fn english() -> Self {
+        Self::new(scoring::english_letter_frequencies())
+    }
+
+    pub fn new(expected: [f64; 26]) -> Self {
+        Self { expected }
+    }
+}
+
+impl Scorer for ChiSquaredScorer {
+    fn name(&self) -> String {
+        "chi2".to_string()
+    }
+
+    fn score(&self, text: &str) -> f64 {
+        -statistics::chi_squared(text, &self.expected)
+    }
+}
+
+// Negated distance between the text's letter entropy and the entropy of a
+// reference distribution. Substitution leaves entropy unchanged, so this
+// separates transposition-like from polyalphabetic-like text.
+pub struct EntropyScorer {
+    target: f64,
+}
+
+impl EntropyScorer {
+    pub fn new(expected: &[f64; 26]) -> Self {
+        Self {
+            target: statistics::distribution_entropy(expected),
+        }
+    }
+}
+
+impl Scorer for EntropyScorer {
+    fn name(&self) -> String {
+        "entropy".to_string()
+    }
+
+    fn score(&self, text: &str) -> f64 {
+        -(statistics::shannon_entropy(text) - self.target).abs()
+    }
+}
+
+// Negated Kullback-Leibler divergence from a reference letter distribution.
+pub struct KlDivergenceScorer {
+    expected: [f64; 26],
+}
+
+impl KlDivergenceScorer {
+    pub fn new(expected: [f64; 26]) -> Self {
+        Self { expected }
+    }
+}
+
+impl Scorer for KlDivergenceScorer {
+    fn name(&self) -> String {
+        "kl".to_string()
+    }
+
+    fn score(&self, text: &str) -> f64 {
+        -statistics::kl_divergence(text, &self.expected)
+    }
+}
+
+// Log probability per letter of the best word segmentation.
+pub struct WordSegmentationScorer {
+    model: Arc<WordModel>,
+}
+
+impl WordSegmentationScorer {
+    pub fn new(model: Arc<WordModel>) -> Self {
+        Self { model }
+    }
+}
+
+impl Scorer for WordSegmentationScorer {
+    fn name(&self) -> String {
+        "words".to_string()
+    }
+
+    fn score(&self, text: &str) -> f64 {
+        let segmentation = self.model.segment(text);
+        let letters: usize = segmentation.words.iter().map(|w| w.text.len()).sum();
+        if letters == 0 {
+            return f64::NEG_INFINITY;
+        }
+        segmentation.log_probability / letters as f64
+    }
+
+    fn matches(&self, text: &str) -> Vec<ScoreMatch> {
+        known_word_matches(&self.model, text)
+    }
+}
+
+// The fit under whichever of several languages suits the text best. The
+// languages come from `language::parse_languages`, which keeps their fits
+// on one scale.
+pub struct LanguageScorer {
+    languages: Vec<Language>,
+}
+
+impl LanguageScorer {
+    pub fn new(languages: Vec<Language>) -> Self {
+        Self { languages }
+    }
+}
+
+impl Scorer for LanguageScorer {
+    fn name(&self) -> String {
+        "language".to_string()
+    }
+
+    fn score(&self, text: &str) -> f64 {
+        self.languages
+            .iter()
+            .map(|language| language.fit(text))
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+}
+
+// As WordSegmentationScorer, but near misses of dictionary words count, at
+// a penalty, so a deliberate misspelling like IQLUSION still scores as a
+// word.
+pub struct FuzzyWordScorer {
+    model: Arc<WordModel>,
+}
+
+impl FuzzyWordScorer {
+    pub fn new(model: Arc<WordModel>) -> Self {
+        Self { model }
+    }
+}
+
+impl Scorer for FuzzyWordScorer {
+    fn name(&self) -> String {
+        "fuzzy".to_string()
+    }
+
+    fn score(&self, text: &str) -> f64 {
+        let segmentation = self.model.segment_fuzzy(text);
+        let letters: usize = segmentation.words.iter().map(|w| w.text.len()).sum();
+        if letters == 0 {
+            return f64::NEG_INFINITY;
+        }
+        segmentation.log_probability / letters as f64
+    }
+
+    fn matches(&self, text: &str) -> Vec<ScoreMatch> {
+        self.model
+            .segment_fuzzy(text)
+            .words
+            .into_iter()
+            .filter_map(|w| match &w.fuzzy {
+                Some(m) => Some(ScoreMatch {
+                    text: format!("{}~{}", w.text, m.word),
+                    position: w.position,
+                }),
+                None if w.known && w.text.len() >= scoring::MIN_SCORED_WORD_LENGTH => {
+                    Some(ScoreMatch {
+                        text: w.text,
+                        position: w.position,
+                    })
+                }
+                None => None,
+            })
+            .collect()
+    }
+}
+
+// Fraction of crib letters reproduced at their positions.
+pub struct CribScorer {
+    cribs: Vec<Crib>,
+}
+
+impl CribScorer {
+    pub fn new(cribs: Vec<Crib>) -> Self {
+        Self { cribs }
+    }
+}
+
+impl Scorer for CribScorer {
+    fn name(&self) -> String {
+        "crib".to_string()
+    }
+
+    fn score(&self, text: &str) -> f64 {
+        let chars: Vec<char> = text.chars().collect();
+        let mut total = 0;
+        let mut matched = 0;
+        for crib in &self.cribs {
+            for (position, c) in crib.letters() {
+                total += 1;
+                if chars.get(position) == Some(&c) {
+                    matched += 1;
+                }
+            }
+        }
+        if total == 0 {
+            return 0.0;
+        }
+        matched as f64 / total as f64
+    }
+
+    // Each run of consecutive crib letters the text reproduces.
+    fn matches(&self, text: &str) -> Vec<ScoreMatch> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut matches = Vec::new();
+        for crib in &self.cribs {
+            let mut run: Option<ScoreMatch> = None;
+            for (position, c) in crib.letters() {
+                if chars.get(position) == Some(&c) {
+                    run.get_or_insert_with(|| ScoreMatch {
+                        text: String::new(),
+                        position,
+                    })
+                    .text
+                    .push(c);
+                } else if let Some(run) = run.take() {
+                    matches.push(run);
+                }
+            }
+            matches.extend(run);
+        }
+        matches
+    }
+}
+
+// A weighted sum of other scorers.
+pub struct WeightedScorer {
+    components: Vec<(f64, Box<dyn Scorer>)>,
+}
+
+impl WeightedScorer {
+    pub fn new(components: Vec<(f64, Box<dyn Scorer>)>) -> Self {
+        Self { components }
+    }
+
+    pub fn components(&self) -> &[(f64, Box<dyn Scorer>)] {
+        &self.components
+    }
+}
+
+impl Scorer for WeightedScorer {
+    fn name(&self) -> String {
+        self.components
+            .iter()
+            .map(|(weight, scorer)| format!("{}:{}", scorer.name(), weight))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    // Zero-weight components are skipped, not multiplied out: 0 times the
+    // NEG_INFINITY that some scorers give an empty text would be NaN.
+    fn score(&self, text: &str) -> f64 {
+        self.components
+            .iter()
+            .filter(|(weight, _)| *weight != 0.0)
+            .map(|(weight, scorer)| weight * scorer.score(text))
+            .sum()
+    }
+
+    // Components that break down further, such as `legacy`, report each of
+    // their parts under the component's name, scaled by its weight.
+    fn explain(&self, text: &str) -> ScoreBreakdown {
+        let components: Vec<ComponentScore> = self
+            .components
+            .iter()
+            .filter(|(weight, _)| *weight != 0.0)
+            .flat_map(|(weight, scorer)| {
+                let breakdown = scorer.explain(text);
+                let nested = breakdown.components.len() > 1;
+                breakdown
+                    .components
+                    .into_iter()
+                    .map(move |part| ComponentScore {
+                        name: if nested {
+                            format!("{}.{}", scorer.name(), part.name)
+                        } else {
+                            part.name
+                        },
+                        raw: part.raw,
+                        weight: weight * part.weight,
+                        contribution: weight * part.contribution,
+                        matches: part.matches,
+                    })
+            })
+            .collect();
+
+        ScoreBreakdown {
+            total: components.iter().map(|c| c.contribution).sum(),
+            components,
+        }
+    }
+}
+
+// What the named scorers in a spec may draw on.
+pub struct ScorerResources {
+    pub ngram_model: Option<Arc<NgramModel>>,
+    pub word_model: Arc<WordModel>,
+    pub cribs: Vec<Crib>,
+    // Letter distribution for the monogram, chi2, entropy and kl scorers.
+    pub reference: [f64; 26],
+    pub languages: Vec<Language>,
+}
+
+pub const SCORER_NAMES: &[&str] = &[
+    "legacy",
+    "monogram",
+    "ngram",
+    "ioc",
+    "periodic-ioc",
+    "dic",
+    "chi2",
+    "entropy",
+    "kl",
+    "words",
+    "fuzzy",
+    "crib",
+    "language",
+];
+
+pub fn build_named_scorer(
+    name: &str,
+    resources: &ScorerResources,
+) -> Result<Box<dyn Scorer>, String> {
+    match name {
+        "legacy" => Ok(Box::new(LegacyScorer)),
+        "monogram" => Ok(Box::new(MonogramScorer::new(&resources.reference))),
+        "ngram" => match &resources.ngram_model {
+            Some(model) => Ok(Box::new(NgramScorer::new(Arc::clone(model)))),
+            None => Err("the ngram scorer needs an n-gram model (--ngrams)".to_string()),
+        },
+        "ioc" => Ok(Box::new(IocScorer::new(IocScorer::ENGLISH))),
+        "periodic-ioc" => Ok(Box::new(PeriodicIocScorer::new(
+            IocScorer::ENGLISH,
+            PeriodicIocScorer::DEFAULT_MAX_PERIOD,
+        ))),
+        "dic" => Ok(Box::new(DigraphIocScorer)),
+        "chi2" => Ok(Box::new(ChiSquaredScorer::new(resources.reference))),
+        "entropy" => Ok(Box::new(EntropyScorer::new(&resources.reference))),
+        "kl" => Ok(Box::new(KlDivergenceScorer::new(resources.reference))),
+        "words" => Ok(Box::new(WordSegmentationScorer::new(Arc::clone(
+            &resources.word_model,
+        )))),
+        "fuzzy" => Ok(Box::new(FuzzyWordScorer::new(Arc::clone(
+            &resources.word_model,
+        )))),
+        "crib" => Ok(Box::new(CribScorer::new(resources.cribs.clone()))),
+        "language" => Ok(Box::new(LanguageScorer::new(resources.languages.clone()))),
+        other => Err(format!(
+            "unknown scorer '{}', expected one of {}",
+            other,
+            SCORER_NAMES.join(", ")
+        )),
+    }
+}
+
+// Builds a weighted scorer from a spec such as `ngram:1.0,words:0.5,crib:2`.
+// A component without a weight counts once.
+pub fn build_scorer(spec: &str, resources: &ScorerResources) -> Result<WeightedScorer, String> {
+    let mut components = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (name, weight) = match part.split_once(':') {
+            Some((name, weight)) => {
+                let weight = weight
+                    .parse::<f64>()
+                    .map_err(|e| format!("invalid weight '{}' for {}: {}", weight, name, e))?;
+                (name, weight)
+            }
+            None => (part, 1.0),
+        };
+        components.push((weight, build_named_scorer(name, resources)?));
+    }
+
+    if components.is_empty() {
+        return Err("scorer spec is empty".to_string());
+    }
+    Ok(WeightedScorer::new(components))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resources() -> ScorerResources {
+        let model = NgramModel::from_counts(2, [("TH", 3), ("HE", 1)]).unwrap();
+        ScorerResources {
+            ngram_model: Some(Arc::new(model)),
+            word_model: Arc::new(WordModel::from_counts([("THE", 10), ("CLOCK", 2)]).unwrap()),
+            cribs: Vec::new(),
+            reference: scoring::english_letter_frequencies(),
+            languages: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn zero_weight_components_do_not_turn_the_score_into_nan() {
+        let scorer = build_scorer("words:0,ngram:1", &resources()).unwrap();
+        assert_eq!(scorer.name(), "words:0,ngram2:1");
+        assert_eq!(
+            WordSegmentationScorer::new(resources().word_model).score(""),
+            f64::NEG_INFINITY
+        );
+
+        let floor = resources().ngram_model.unwrap().floor();
+        assert_eq!(scorer.score(""), floor);
+        let breakdown = scorer.explain("");
+        assert_eq!(breakdown.total, floor);
+        assert_eq!(breakdown.components.len(), 1);
+    }
+
+    #[test]
+    fn weighted_scorer_sums_weighted_components() {
+        let scorer = build_scorer("crib:2,ioc", &resources()).unwrap();
+        let text = "THETHE";
+        let expected = 2.0 * CribScorer::new(Vec::new()).score(text)
+            + IocScorer::new(IocScorer::ENGLISH).score(text);
+        assert_eq!(scorer.score(text), expected);
+        assert_eq!(scorer.explain(text).total, expected);
+    }
+
+    #[test]
+    fn legacy_breakdown_adds_up_to_the_legacy_score() {
+        let text = "BERLINCLOCKTHEEAST";
+        let breakdown = LegacyScorer.explain(text);
+        assert!((breakdown.total - scoring::score_text(text)).abs() < 1e-9);
+        let words = breakdown
+            .components
+            .iter()
+            .find(|c| c.name == "words")
+            .unwrap();
+        assert!(words
+            .matches
+            .iter()
+            .any(|m| m.text == "CLOCK" && m.position == 6));
+
+        let weighted = build_scorer("legacy:2,ioc", &resources()).unwrap();
+        let breakdown = weighted.explain(text);
+        let names: Vec<&str> = breakdown
+            .components
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "legacy.letters",
+                "legacy.bigrams",
+                "legacy.trigrams",
+                "legacy.words",
+                "legacy.crib-chars",
+                "ioc"
+            ]
+        );
+        assert_eq!(breakdown.components[3].weight, 2.0 * words.weight);
+        assert!((breakdown.total - weighted.score(text)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn crib_matches_are_the_reproduced_runs() {
+        let scorer = CribScorer::new(vec![Crib::new(2, "CLOCK")]);
+        let text = "XXCLOXKX";
+        assert_eq!(scorer.score(text), 0.8);
+        let matches: Vec<(String, usize)> = scorer
+            .matches(text)
+            .into_iter()
+            .map(|m| (m.text, m.position))
+            .collect();
+        assert_eq!(matches, [("CLO".to_string(), 2), ("K".to_string(), 6)]);
+    }
+
+    #[test]
+    fn fuzzy_scorer_reports_near_misses() {
+        let model =
+            WordModel::from_counts([("BERLIN", 1000), ("CLOCK", 1000), ("THE", 100000)]).unwrap();
+        let scorer = FuzzyWordScorer::new(Arc::new(model));
+        assert!(scorer.score("BERLINCLOCX") > scorer.score("BERLINQZXJW"));
+        let matches: Vec<(String, usize)> = scorer
+            .matches("BERLINCLOCX")
+            .into_iter()
+            .map(|m| (m.text, m.position))
+            .collect();
+        assert_eq!(
+            matches,
+            [("BERLIN".to_string(), 0), ("CLOCX~CLOCK".to_string(), 6)]
+        );
+        assert_eq!(scorer.score(""), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn build_scorer_rejects_bad_specs() {
+        assert!(build_scorer("", &resources()).is_err());
+        assert!(build_scorer("nonsense", &resources()).is_err());
+        assert!(build_scorer("ioc:heavy", &resources()).is_err());
+        let no_model = ScorerResources {
+            ngram_model: None,
+            ..resources()
+        };
+        assert!(build_scorer("ngram", &no_model).is_err());
+    }
+}
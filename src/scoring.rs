@@ -1,8 +1,13 @@
 // scoring.rs
 
+use crate::segmentation::WordModel;
 use lazy_static::lazy_static;
 use std::collections::{BTreeSet, HashMap, HashSet};
 
+// Words shorter than this are too easy to find in random letters to count
+// towards the word score.
+pub const MIN_SCORED_WORD_LENGTH: usize = 3;
+
 lazy_static! {
     static ref LETTER_FREQUENCIES: HashMap<char, f64> = {
         let mut freq_map = HashMap::new();
@@ -657,15 +662,105 @@ static ref BIGRAM_FREQUENCIES: HashMap<&'static str, f64> = {
         // ... (Add more trigrams and their frequencies)
         freq_map
     };
+
+    static ref DEFAULT_WORD_MODEL: WordModel = WordModel::from_words(COMMON_WORDS.iter()).unwrap();
+
+    // Every prefix of a scored common word, mapped to the word it spells out
+    // when it is one, so a scan can stop as soon as no word continues.
+    static ref SCORED_WORD_PREFIXES: HashMap<&'static str, Option<&'static str>> = {
+        let mut prefixes = HashMap::new();
+        for &word in COMMON_WORDS.iter().filter(|w| w.len() >= MIN_SCORED_WORD_LENGTH) {
+            for end in 1..word.len() {
+                prefixes.entry(&word[..end]).or_insert(None);
+            }
+            prefixes.insert(word, Some(word));
+        }
+        prefixes
+    };
+}
+
+// LETTER_FREQUENCIES as an A-Z indexed table.
+pub fn english_letter_frequencies() -> [f64; 26] {
+    let mut frequencies = [0.0; 26];
+    for (i, c) in ('A'..='Z').enumerate() {
+        frequencies[i] = LETTER_FREQUENCIES[&c];
+    }
+    frequencies
+}
+
+pub fn common_words() -> impl Iterator<Item = &'static str> {
+    COMMON_WORDS.iter().copied()
+}
+
+// A uniform word model over COMMON_WORDS, used when no word counts are given.
+pub fn default_word_model() -> &'static WordModel {
+    &DEFAULT_WORD_MODEL
+}
+
+// The positions of the tabled trigrams that `score_text` rewards.
+pub fn matched_trigrams(text: &str) -> Vec<(usize, &'static str)> {
+    let text_upper = text.to_uppercase();
+    text_upper
+        .as_bytes()
+        .windows(3)
+        .enumerate()
+        .filter_map(|(i, trigram)| {
+            let trigram = std::str::from_utf8(trigram).ok()?;
+            TRIGRAM_FREQUENCIES
+                .get_key_value(trigram)
+                .map(|(&key, _)| (i, key))
+        })
+        .collect()
+}
+
+// Every occurrence of a COMMON_WORDS entry of at least
+// MIN_SCORED_WORD_LENGTH letters, overlapping ones included. This is a
+// plain substring scan, cheap enough for every sweep candidate; the `words`
+// scorer segments the text properly instead.
+pub fn matched_words(text: &str) -> Vec<(usize, &'static str)> {
+    matched_upper_words(&text.to_uppercase())
+}
+
+fn matched_upper_words(text_upper: &str) -> Vec<(usize, &'static str)> {
+    let mut matches = Vec::new();
+    for start in 0..text_upper.len() {
+        for end in start + 1..=text_upper.len() {
+            match text_upper
+                .get(start..end)
+                .and_then(|prefix| SCORED_WORD_PREFIXES.get(prefix))
+            {
+                Some(Some(word)) => matches.push((start, *word)),
+                Some(None) => {}
+                None => break,
+            }
+        }
+    }
+    matches
+}
+
+// One weighted part of `score_text`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextScoreComponent {
+    pub name: &'static str,
+    pub raw: f64,
+    pub weight: f64,
 }
 
 pub fn score_text(text: &str) -> f64 {
+    score_text_components(text)
+        .iter()
+        .map(|component| component.raw * component.weight)
+        .sum()
+}
+
+// The parts `score_text` sums: letter, bigram and trigram frequency totals,
+// the share of letters in known words, and how close the share of crib
+// letters comes to the expected one.
+pub fn score_text_components(text: &str) -> [TextScoreComponent; 5] {
     let mut letter_score = 0.0;
     let mut bigram_score = 0.0;
     let mut trigram_score = 0.0;
-    let mut word_score = 0.0;
 
-    let text_length = text.len() as f64;
     let text_upper = text.to_uppercase();
 
     for c in text_upper.chars() {
@@ -686,18 +781,19 @@ pub fn score_text(text: &str) -> f64 {
         }
     }
 
-    let words: &[&str] = &text_upper.split_whitespace().collect::<Vec<&str>>();
-    let total_words = words.len() as f64;
-
-    for word in words {
-        if COMMON_WORDS.contains(word) {
-            word_score += 1.0;
-        }
+    // Candidates carry no spaces, so the word score is the share of letters
+    // covered by common words found anywhere in the text.
+    let mut in_word = vec![false; text_upper.len()];
+    for (start, word) in matched_upper_words(&text_upper) {
+        in_word[start..start + word.len()].fill(true);
     }
+    let scaled_word_score = if in_word.is_empty() {
+        0.0
+    } else {
+        in_word.iter().filter(|&&covered| covered).count() as f64 / in_word.len() as f64
+    };
 
-    let scaled_word_score = word_score / total_words;
-
-    let known_fragments = vec!["EAST", "BERLIN", "CLOCK", "NORTHEAST"];
+    let known_fragments = ["EAST", "BERLIN", "CLOCK", "NORTHEAST"];
     let known_chars: HashSet<char> = known_fragments.iter().flat_map(|s| s.chars()).collect();
     let total_chars = text.len() as f64;
     let known_char_count = text.chars().filter(|c| known_chars.contains(c)).count() as f64;
@@ -706,17 +802,64 @@ pub fn score_text(text: &str) -> f64 {
     let expected_known_char_percentage = 24.0 / 97.0;
     let percentage_score = 1.0 - (known_char_percentage - expected_known_char_percentage).abs();
 
-    let letter_weight = 0.5;
-    let bigram_weight = 0.3;
-    let trigram_weight = 0.2;
-    let word_weight = 0.4;
-    let percentage_weight = 0.9;
+    [
+        TextScoreComponent {
+            name: "letters",
+            raw: letter_score,
+            weight: 0.5,
+        },
+        TextScoreComponent {
+            name: "bigrams",
+            raw: bigram_score,
+            weight: 0.3,
+        },
+        TextScoreComponent {
+            name: "trigrams",
+            raw: trigram_score,
+            weight: 0.2,
+        },
+        TextScoreComponent {
+            name: "words",
+            raw: scaled_word_score,
+            weight: 0.4,
+        },
+        TextScoreComponent {
+            name: "crib-chars",
+            raw: percentage_score,
+            weight: 0.9,
+        },
+    ]
+}
 
-    let raw_score = letter_score * letter_weight
-        + bigram_score * bigram_weight
-        + trigram_score * trigram_weight
-        + scaled_word_score * word_weight
-        + percentage_score * percentage_weight;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    raw_score
-}
\ No newline at end of file
+    #[test]
+    fn matched_words_finds_overlapping_common_words() {
+        let matches = matched_words("xberlinclockthere");
+        for expected in [(1, "BERLIN"), (7, "CLOCK"), (12, "THERE"), (13, "HERE")] {
+            assert!(matches.contains(&expected), "missing {:?}", expected);
+        }
+        // Two-letter words are too easy to hit by chance to count.
+        assert!(matches
+            .iter()
+            .all(|(_, w)| w.len() >= MIN_SCORED_WORD_LENGTH));
+        assert!(matched_words("").is_empty());
+    }
+
+    #[test]
+    fn word_component_is_the_share_of_letters_in_common_words() {
+        let words = |text: &str| score_text_components(text)[3];
+        assert_eq!(words("XBERLINCLOCKX").name, "words");
+        assert_eq!(words("XBERLINCLOCKX").raw, 11.0 / 13.0);
+        assert_eq!(words("QQQQ").raw, 0.0);
+        assert_eq!(words("").raw, 0.0);
+
+        let total: f64 = score_text_components("BERLINCLOCK")
+            .iter()
+            .map(|c| c.raw * c.weight)
+            .sum();
+        assert_eq!(score_text("BERLINCLOCK"), total);
+    }
+}
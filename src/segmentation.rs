@@ -0,0 +1,431 @@
+// segmentation.rs
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::OnceLock;
+
+// Unigram word probabilities for splitting unspaced plaintext into words.
+// Words outside the model are allowed but priced like Norvig's unknown-word
+// estimate, log10(10 / (total * 10^length)), so long gibberish runs lose to
+// any split into real words.
+#[derive(Debug, Clone)]
+pub struct WordModel {
+    log_probs: HashMap<String, f64>,
+    max_word_length: usize,
+    total: f64,
+    // Single-letter deletions of every word long enough for fuzzy matching,
+    // with the deleted position, built on first use.
+    deletions: OnceLock<HashMap<String, Vec<(String, usize)>>>,
+}
+
+// Shorter words have so many one-letter neighbours that near misses say
+// nothing, so fuzzy matching only considers dictionary words this long.
+pub const MIN_FUZZY_WORD_LENGTH: usize = 5;
+
+// Log10 cost of accepting a near miss instead of the dictionary word.
+pub const FUZZY_LOG_PENALTY: f64 = -2.0;
+
+// How a text word differs from the dictionary word it nearly matches, in
+// the manner of IQLUSION for ILLUSION in K1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anomaly {
+    Substituted { expected: char, found: char },
+    Inserted { found: char },
+    Deleted { expected: char },
+}
+
+impl Anomaly {
+    // The letter the text has that the dictionary word does not.
+    pub fn found(&self) -> Option<char> {
+        match self {
+            Anomaly::Substituted { found, .. } | Anomaly::Inserted { found } => Some(*found),
+            Anomaly::Deleted { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    pub word: String,
+    pub anomaly: Anomaly,
+    // Where the anomaly sits: within the word from `WordModel::fuzzy_match`,
+    // within the text in a segmentation.
+    pub position: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+    pub text: String,
+    pub position: usize,
+    pub known: bool,
+    pub fuzzy: Option<FuzzyMatch>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segmentation {
+    pub words: Vec<Word>,
+    pub log_probability: f64,
+}
+
+impl Segmentation {
+    pub fn to_spaced_string(&self) -> String {
+        self.words
+            .iter()
+            .map(|w| w.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // Fraction of letters that fall inside known words of at least
+    // min_length letters.
+    pub fn known_coverage(&self, min_length: usize) -> f64 {
+        let total: usize = self.words.iter().map(|w| w.text.len()).sum();
+        if total == 0 {
+            return 0.0;
+        }
+        let known: usize = self
+            .words
+            .iter()
+            .filter(|w| w.known && w.text.len() >= min_length)
+            .map(|w| w.text.len())
+            .sum();
+        known as f64 / total as f64
+    }
+
+    pub fn anomalies(&self) -> Vec<&FuzzyMatch> {
+        self.words.iter().filter_map(|w| w.fuzzy.as_ref()).collect()
+    }
+}
+
+impl WordModel {
+    pub fn from_counts<I, S>(counts: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = (S, u64)>,
+        S: AsRef<str>,
+    {
+        let mut raw_counts: HashMap<String, u64> = HashMap::new();
+        for (word, count) in counts {
+            let word = word.as_ref().to_ascii_uppercase();
+            if word.is_empty() || !word.chars().all(|c| c.is_ascii_uppercase()) {
+                return Err(format!("'{}' is not a word over A-Z", word));
+            }
+            *raw_counts.entry(word).or_insert(0) += count;
+        }
+
+        let total = raw_counts.values().sum::<u64>() as f64;
+        if total == 0.0 {
+            return Err("word counts are empty".to_string());
+        }
+
+        let max_word_length = raw_counts.keys().map(String::len).max().unwrap_or(1);
+        let log_probs = raw_counts
+            .into_iter()
+            .map(|(word, count)| (word, (count as f64 / total).log10()))
+            .collect();
+
+        Ok(Self {
+            log_probs,
+            max_word_length,
+            total,
+            deletions: OnceLock::new(),
+        })
+    }
+
+    // Every word equally likely, for plain word lists without counts.
+    pub fn from_words<I, S>(words: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::from_counts(words.into_iter().map(|w| (w, 1)))
+    }
+
+    // Reads `WORD COUNT` lines, the same layout as the n-gram files. Lines
+    // holding only a word count as 1.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut counts = Vec::new();
+
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            let Some(word) = fields.next() else {
+                continue;
+            };
+            let count = match fields.next() {
+                Some(count) => count.parse::<u64>().map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {}: bad count '{}': {}", line_number + 1, count, e),
+                    )
+                })?,
+                None => 1,
+            };
+            counts.push((word.to_string(), count));
+        }
+
+        Self::from_counts(counts).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn log_probability(&self, word: &str) -> Option<f64> {
+        self.log_probs.get(word).copied()
+    }
+
+    pub fn unknown_log_probability(&self, length: usize) -> f64 {
+        1.0 - self.total.log10() - length as f64
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.log_probs.contains_key(word)
+    }
+
+    pub fn max_word_length(&self) -> usize {
+        self.max_word_length
+    }
+
+    fn deletions(&self) -> &HashMap<String, Vec<(String, usize)>> {
+        self.deletions.get_or_init(|| {
+            let mut deletions: HashMap<String, Vec<(String, usize)>> = HashMap::new();
+            for word in self.log_probs.keys() {
+                if word.len() < MIN_FUZZY_WORD_LENGTH {
+                    continue;
+                }
+                for i in 0..word.len() {
+                    let variant = format!("{}{}", &word[..i], &word[i + 1..]);
+                    deletions
+                        .entry(variant)
+                        .or_default()
+                        .push((word.clone(), i));
+                }
+            }
+            deletions
+        })
+    }
+
+    // The most probable dictionary word within one substitution, insertion
+    // or deletion of the text word, if the text word is not itself known.
+    pub fn fuzzy_match(&self, text: &str) -> Option<FuzzyMatch> {
+        if self.contains(text) || !text.is_ascii() {
+            return None;
+        }
+        let deletions = self.deletions();
+        let letter = |word: &str, i: usize| word.as_bytes()[i] as char;
+        let mut matches = Vec::new();
+
+        // The text is missing a letter of the dictionary word.
+        for (word, i) in deletions.get(text).into_iter().flatten() {
+            let expected = letter(word, *i);
+            matches.push((word.clone(), Anomaly::Deleted { expected }, *i));
+        }
+        for i in 0..text.len() {
+            let variant = format!("{}{}", &text[..i], &text[i + 1..]);
+            let found = letter(text, i);
+            // The text has one letter changed.
+            for (word, j) in deletions.get(&variant).into_iter().flatten() {
+                if *j == i {
+                    let expected = letter(word, i);
+                    matches.push((word.clone(), Anomaly::Substituted { expected, found }, i));
+                }
+            }
+            // The text has an extra letter.
+            if variant.len() >= MIN_FUZZY_WORD_LENGTH && self.contains(&variant) {
+                matches.push((variant, Anomaly::Inserted { found }, i));
+            }
+        }
+
+        matches
+            .into_iter()
+            .max_by(|a, b| {
+                self.log_probs[&a.0]
+                    .total_cmp(&self.log_probs[&b.0])
+                    .then_with(|| b.0.cmp(&a.0))
+            })
+            .map(|(word, anomaly, position)| FuzzyMatch {
+                word,
+                anomaly,
+                position,
+            })
+    }
+
+    // Viterbi search for the most probable split of the text into words.
+    pub fn segment(&self, text: &str) -> Segmentation {
+        self.segment_with(text, false)
+    }
+
+    // As `segment`, but a word one edit away from a dictionary word of at
+    // least MIN_FUZZY_WORD_LENGTH letters is also accepted, at the
+    // dictionary word's probability plus FUZZY_LOG_PENALTY.
+    pub fn segment_fuzzy(&self, text: &str) -> Segmentation {
+        self.segment_with(text, true)
+    }
+
+    fn segment_with(&self, text: &str, fuzzy: bool) -> Segmentation {
+        let letters: String = text
+            .chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        let n = letters.len();
+
+        // best[i] is the log probability of the best split of letters[..i],
+        // and start[i] where its last word begins; near[i] holds that word's
+        // fuzzy match when it was taken as one.
+        let mut best = vec![f64::NEG_INFINITY; n + 1];
+        let mut start = vec![0; n + 1];
+        let mut near: Vec<Option<FuzzyMatch>> = vec![None; n + 1];
+        best[0] = 0.0;
+
+        // A near miss may run one letter longer than any dictionary word.
+        let max_length = self.max_word_length + usize::from(fuzzy);
+        for end in 1..=n {
+            for begin in end.saturating_sub(max_length)..end {
+                let word = &letters[begin..end];
+                let mut fuzzy_match = None;
+                let cost = match self.log_probability(word) {
+                    Some(log_probability) => log_probability,
+                    None => {
+                        let unknown = self.unknown_log_probability(word.len());
+                        let candidate = if fuzzy && word.len() + 1 >= MIN_FUZZY_WORD_LENGTH {
+                            self.fuzzy_match(word)
+                        } else {
+                            None
+                        };
+                        match candidate {
+                            Some(m) if self.log_probs[&m.word] + FUZZY_LOG_PENALTY > unknown => {
+                                let cost = self.log_probs[&m.word] + FUZZY_LOG_PENALTY;
+                                fuzzy_match = Some(m);
+                                cost
+                            }
+                            _ => unknown,
+                        }
+                    }
+                };
+                if best[begin] + cost > best[end] {
+                    best[end] = best[begin] + cost;
+                    start[end] = begin;
+                    near[end] = fuzzy_match;
+                }
+            }
+        }
+
+        let mut words = Vec::new();
+        let mut end = n;
+        while end > 0 {
+            let begin = start[end];
+            let text = letters[begin..end].to_string();
+            words.push(Word {
+                known: self.contains(&text),
+                fuzzy: near[end].take().map(|m| FuzzyMatch {
+                    position: begin + m.position,
+                    ..m
+                }),
+                text,
+                position: begin,
+            });
+            end = begin;
+        }
+        words.reverse();
+
+        Segmentation {
+            words,
+            log_probability: best[n],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model() -> WordModel {
+        WordModel::from_counts([
+            ("BERLIN", 20),
+            ("CLOCK", 20),
+            ("LOCK", 10),
+            ("BE", 30),
+            ("ILLUSION", 5),
+            ("EAST", 10),
+            ("NEAR", 5),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn segments_into_the_most_probable_words() {
+        let segmentation = model().segment("berlinclock");
+        assert_eq!(segmentation.to_spaced_string(), "BERLIN CLOCK");
+        assert_eq!(segmentation.words[1].position, 6);
+        assert!(segmentation.words.iter().all(|w| w.known));
+        assert_eq!(segmentation.known_coverage(3), 1.0);
+        let expected = (20.0f64 / 100.0).log10() * 2.0;
+        assert!((segmentation.log_probability - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn unknown_letters_become_their_own_word() {
+        let segmentation = model().segment("EASTQXBERLIN");
+        assert_eq!(segmentation.to_spaced_string(), "EAST QX BERLIN");
+        assert!(!segmentation.words[1].known);
+        assert_eq!(segmentation.known_coverage(3), 10.0 / 12.0);
+    }
+
+    #[test]
+    fn empty_text_has_no_words() {
+        for text in ["", "123 !"] {
+            let segmentation = model().segment(text);
+            assert!(segmentation.words.is_empty());
+            assert_eq!(segmentation.log_probability, 0.0);
+            assert_eq!(segmentation.known_coverage(3), 0.0);
+            assert!(model().segment_fuzzy(text).words.is_empty());
+        }
+    }
+
+    #[test]
+    fn fuzzy_match_finds_one_edit_from_long_enough_words() {
+        let model = model();
+        assert_eq!(
+            model.fuzzy_match("IQLUSION"),
+            Some(FuzzyMatch {
+                word: "ILLUSION".to_string(),
+                anomaly: Anomaly::Substituted {
+                    expected: 'L',
+                    found: 'Q'
+                },
+                position: 1,
+            })
+        );
+        assert_eq!(
+            model.fuzzy_match("CLOK").map(|m| (m.word, m.anomaly)),
+            Some(("CLOCK".to_string(), Anomaly::Deleted { expected: 'C' }))
+        );
+        assert_eq!(
+            model.fuzzy_match("CLOCKK").map(|m| m.word),
+            Some("CLOCK".to_string())
+        );
+
+        // CLOCK is long enough for near misses, LOCK and EAST are not.
+        assert_eq!(MIN_FUZZY_WORD_LENGTH, 5);
+        assert!(model.fuzzy_match("CLOCX").is_some());
+        assert!(model.fuzzy_match("LOCX").is_none());
+        assert!(model.fuzzy_match("EASX").is_none());
+        // Two edits are too many, and known words are not near misses.
+        assert!(model.fuzzy_match("CLXCX").is_none());
+        assert!(model.fuzzy_match("CLOCK").is_none());
+    }
+
+    #[test]
+    fn fuzzy_segmentation_reports_the_anomaly_in_text_positions() {
+        let segmentation = model().segment_fuzzy("BERLINCLOCX");
+        assert_eq!(segmentation.to_spaced_string(), "BERLIN CLOCX");
+        let anomalies = segmentation.anomalies();
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].word, "CLOCK");
+        assert_eq!(anomalies[0].position, 10);
+        assert_eq!(anomalies[0].anomaly.found(), Some('X'));
+
+        assert_eq!(model().segment_fuzzy("EASX").anomalies().len(), 0);
+        assert!(model().segment("BERLINCLOCX").anomalies().is_empty());
+    }
+}
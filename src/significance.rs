@@ -0,0 +1,203 @@
+// significance.rs
+
+use crate::scorer::Scorer;
+use crate::seeding;
+use crate::substitution;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rayon::prelude::*;
+
+// How far a score sits above what the scorer gives texts with no message in
+// them. The p-values are upper tails: the chance a random text scores at
+// least this well.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Significance {
+    pub z_score: f64,
+    pub p_value: f64,
+    pub empirical_p_value: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct CalibrationConfig {
+    pub samples: usize,
+    // When set, half of the samples are the ciphertext decrypted under random
+    // keys of this length with `substitution::polyalphabetic_substitution`;
+    // the rest are random shuffles of the ciphertext.
+    pub random_key: Option<(String, usize)>,
+    // Each sample draws from its own stream of this seed.
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Baseline {
+    scores: Vec<f64>,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl Baseline {
+    pub fn from_scores(mut scores: Vec<f64>) -> Self {
+        scores.retain(|s| s.is_finite());
+        scores.sort_by(f64::total_cmp);
+
+        let n = scores.len().max(1) as f64;
+        let mean = scores.iter().sum::<f64>() / n;
+        let variance = scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+
+        Self {
+            scores,
+            mean,
+            std_dev: variance.sqrt(),
+        }
+    }
+
+    pub fn samples(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.scores.is_empty() {
+            return f64::NAN;
+        }
+        let index = ((self.scores.len() - 1) as f64 * q.clamp(0.0, 1.0)).round() as usize;
+        self.scores[index]
+    }
+
+    pub fn significance(&self, score: f64) -> Significance {
+        let z_score = if self.std_dev > 0.0 {
+            (score - self.mean) / self.std_dev
+        } else {
+            0.0
+        };
+
+        // Add-one smoothing keeps the empirical tail above zero for scores
+        // beyond every sample.
+        let at_least = self.scores.len() - self.scores.partition_point(|&s| s < score);
+        let empirical_p_value = (at_least + 1) as f64 / (self.scores.len() + 1) as f64;
+
+        Significance {
+            z_score,
+            p_value: 0.5 * erfc(z_score / std::f64::consts::SQRT_2),
+            empirical_p_value,
+        }
+    }
+}
+
+// Scores random stand-ins for the plaintext and fits a normal distribution
+// to them.
+pub fn calibrate(ciphertext: &str, scorer: &dyn Scorer, config: &CalibrationConfig) -> Baseline {
+    let cipher_chars: Vec<char> = ciphertext.chars().collect();
+
+    let scores: Vec<f64> = (0..config.samples)
+        .into_par_iter()
+        .map(|i| {
+            let mut rng = seeding::stream(config.seed, i);
+            let text = match &config.random_key {
+                Some((alphabet, key_length)) if i % 2 == 1 => {
+                    random_key_decryption(ciphertext, alphabet, *key_length, &mut rng)
+                }
+                _ => {
+                    let mut shuffled = cipher_chars.clone();
+                    shuffled.shuffle(&mut rng);
+                    shuffled.into_iter().collect()
+                }
+            };
+            scorer.score(&text)
+        })
+        .collect();
+
+    Baseline::from_scores(scores)
+}
+
+fn random_key_decryption<R: Rng>(
+    ciphertext: &str,
+    alphabet: &str,
+    key_length: usize,
+    rng: &mut R,
+) -> String {
+    let alphabet_chars: Vec<char> = alphabet.chars().collect();
+    if alphabet_chars.is_empty() {
+        return ciphertext.to_string();
+    }
+    let key: String = (0..key_length.max(1))
+        .map(|_| alphabet_chars[rng.gen_range(0..alphabet_chars.len())])
+        .collect();
+    substitution::polyalphabetic_substitution(ciphertext, &key, alphabet)
+}
+
+// Complementary error function, Abramowitz and Stegun 7.1.26 (absolute
+// error below 1.5e-7).
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erfc = polynomial * (-x * x).exp();
+    if x >= 0.0 {
+        erfc
+    } else {
+        2.0 - erfc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scorer::MonogramScorer;
+
+    const CIPHERTEXT: &str = "OBKRUOXOGHULBSOLIFBBWFLRVQQPRNGKSSOTWTQSJQSSEKZZWATJKLUDIAWINFBNYPVTTMZFPKWGDKZXTJCDIGKUHUAUEKCAR";
+
+    #[test]
+    fn random_keys_from_an_empty_alphabet_leave_the_ciphertext() {
+        let mut rng = seeding::rng(1);
+        assert_eq!(
+            random_key_decryption(CIPHERTEXT, "", 6, &mut rng),
+            CIPHERTEXT
+        );
+
+        let config = CalibrationConfig {
+            samples: 20,
+            random_key: Some((String::new(), 6)),
+            seed: 1,
+        };
+        assert_eq!(
+            calibrate(CIPHERTEXT, &MonogramScorer::english(), &config).samples(),
+            20
+        );
+    }
+
+    #[test]
+    fn calibration_is_reproducible_from_its_seed() {
+        let config = CalibrationConfig {
+            samples: 50,
+            random_key: Some(("KRYPTOSABCDEFGHIJLMNQUVWXZ".to_string(), 7)),
+            seed: 42,
+        };
+        let scorer = MonogramScorer::english();
+        let first = calibrate(CIPHERTEXT, &scorer, &config);
+        let second = calibrate(CIPHERTEXT, &scorer, &config);
+        assert_eq!(first.scores, second.scores);
+        // Shuffles keep the letters, so half of the samples match the
+        // ciphertext's own monogram score.
+        let own = scorer.score(CIPHERTEXT);
+        assert!(first.scores.iter().filter(|&&s| s == own).count() >= 25);
+    }
+
+    #[test]
+    fn significance_of_baseline_scores() {
+        let baseline = Baseline::from_scores(vec![1.0, 2.0, 3.0, 4.0, 5.0, f64::NAN]);
+        assert_eq!(baseline.samples(), 5);
+        assert_eq!(baseline.mean, 3.0);
+        assert_eq!(baseline.std_dev, 2.5f64.sqrt());
+        assert_eq!(baseline.quantile(0.5), 3.0);
+
+        let at_mean = baseline.significance(3.0);
+        assert_eq!(at_mean.z_score, 0.0);
+        assert!((at_mean.p_value - 0.5).abs() < 1e-6);
+        assert_eq!(at_mean.empirical_p_value, 4.0 / 6.0);
+
+        let beyond = baseline.significance(10.0);
+        assert_eq!(beyond.empirical_p_value, 1.0 / 6.0);
+        assert!(beyond.p_value < 1e-3);
+    }
+}
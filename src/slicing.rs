@@ -0,0 +1,143 @@
+// slicing.rs
+
+use crate::statistics;
+use crate::substitution::{self, CipherFamily, Tableau};
+
+// Solves a periodic key column by column. Every letter in a column shares a
+// key letter, so each column is a simple shift: the shift whose decryption
+// best fits the reference letter distribution is taken as that column's key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnFit {
+    pub shift: usize,
+    pub chi_squared: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SliceSolution {
+    pub period: usize,
+    pub shifts: Vec<usize>,
+    pub column_chi_squared: Vec<f64>,
+    pub plaintext: String,
+}
+
+impl SliceSolution {
+    pub fn key_string(&self, tableau: &Tableau) -> String {
+        self.shifts.iter().map(|&s| tableau.key_letter(s)).collect()
+    }
+
+    pub fn mean_chi_squared(&self) -> f64 {
+        self.column_chi_squared.iter().sum::<f64>() / self.column_chi_squared.len().max(1) as f64
+    }
+}
+
+// Every shift for one column, best fit first.
+pub fn fit_column(
+    column: &str,
+    family: CipherFamily,
+    tableau: &Tableau,
+    expected: &[f64; 26],
+) -> Vec<ColumnFit> {
+    let mut fits: Vec<ColumnFit> = (0..26)
+        .map(|shift| {
+            let plaintext: String = column
+                .chars()
+                .filter_map(|c| tableau.decrypt_char(family, c, shift))
+                .collect();
+            ColumnFit {
+                shift,
+                chi_squared: statistics::chi_squared(&plaintext, expected),
+            }
+        })
+        .collect();
+    fits.sort_by(|a, b| {
+        a.chi_squared
+            .total_cmp(&b.chi_squared)
+            .then(a.shift.cmp(&b.shift))
+    });
+    fits
+}
+
+pub fn solve_period(
+    ciphertext: &str,
+    period: usize,
+    family: CipherFamily,
+    tableau: &Tableau,
+    expected: &[f64; 26],
+) -> SliceSolution {
+    let period = period.max(1);
+    let mut columns = vec![String::new(); period];
+    for (i, c) in ciphertext.chars().enumerate() {
+        columns[i % period].push(c);
+    }
+
+    let (shifts, column_chi_squared) = columns
+        .iter()
+        .map(|column| {
+            let best = &fit_column(column, family, tableau, expected)[0];
+            (best.shift, best.chi_squared)
+        })
+        .unzip();
+
+    let mut solution = SliceSolution {
+        period,
+        shifts,
+        column_chi_squared,
+        plaintext: String::new(),
+    };
+    solution.plaintext =
+        substitution::periodic_decrypt(ciphertext, &solution.key_string(tableau), family, tableau);
+    solution
+}
+
+pub fn solve_periods(
+    ciphertext: &str,
+    max_period: usize,
+    family: CipherFamily,
+    tableau: &Tableau,
+    expected: &[f64; 26],
+) -> Vec<SliceSolution> {
+    (1..=max_period)
+        .map(|period| solve_period(ciphertext, period, family, tableau, expected))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corpus;
+    use crate::scoring;
+
+    #[test]
+    fn slicing_recovers_a_periodic_key() {
+        let plaintext = corpus::normalize(corpus::KRYPTOS_PLAINTEXT);
+        let expected = scoring::english_letter_frequencies();
+        for (family, tableau) in [
+            (CipherFamily::Vigenere, Tableau::straight()),
+            (CipherFamily::Beaufort, Tableau::straight()),
+            (CipherFamily::Vigenere, Tableau::quagmire3("KRYPTOS")),
+        ] {
+            let ciphertext = substitution::periodic_encrypt(&plaintext, "LAYER", family, &tableau);
+            let solutions = solve_periods(&ciphertext, 6, family, &tableau, &expected);
+            assert_eq!(solutions.len(), 6);
+            let solution = &solutions[4];
+            assert_eq!(solution.key_string(&tableau), "LAYER");
+            assert_eq!(solution.plaintext, plaintext);
+            assert!(solution.mean_chi_squared() < solutions[3].mean_chi_squared());
+        }
+    }
+
+    #[test]
+    fn column_fits_are_ranked_best_first() {
+        let fits = fit_column(
+            "EEEEAT",
+            CipherFamily::Vigenere,
+            &Tableau::straight(),
+            &scoring::english_letter_frequencies(),
+        );
+        assert_eq!(fits.len(), 26);
+        assert_eq!(fits[0].shift, 0);
+        assert!(fits
+            .windows(2)
+            .all(|w| w[0].chi_squared <= w[1].chi_squared));
+    }
+}
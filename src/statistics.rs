@@ -0,0 +1,303 @@
+// statistics.rs
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+// Expected index of coincidence for English plaintext and for uniformly
+// random letters.
+pub const ENGLISH_IOC: f64 = 0.0667;
+pub const RANDOM_IOC: f64 = 1.0 / 26.0;
+
+// The A-Z letters of the text, uppercased, with everything else dropped.
+pub fn letters(text: &str) -> Vec<u8> {
+    text.bytes()
+        .map(|b| b.to_ascii_uppercase())
+        .filter(u8::is_ascii_uppercase)
+        .collect()
+}
+
+// Occurrences of each letter A-Z, ignoring everything else.
+pub fn letter_counts(text: &str) -> [usize; 26] {
+    let mut counts = [0; 26];
+    for b in text.bytes() {
+        let b = b.to_ascii_uppercase();
+        if b.is_ascii_uppercase() {
+            counts[(b - b'A') as usize] += 1;
+        }
+    }
+    counts
+}
+
+// The probability that two letters drawn without replacement match. Around
+// 0.067 for English and 0.038 for uniformly random letters.
+pub fn index_of_coincidence(text: &str) -> f64 {
+    let counts = letter_counts(text);
+    let total: usize = counts.iter().sum();
+    if total < 2 {
+        return 0.0;
+    }
+
+    let matches: usize = counts.iter().map(|&n| n * n.saturating_sub(1)).sum();
+    matches as f64 / (total * (total - 1)) as f64
+}
+
+// The index of coincidence over overlapping letter pairs. Monoalphabetic
+// substitution leaves it unchanged while transposition lowers it, so it
+// tracks how much of the original letter order a text keeps.
+pub fn digraph_ioc(text: &str) -> f64 {
+    let letters = letters(text);
+    let mut counts = vec![0usize; 26 * 26];
+    for pair in letters.windows(2) {
+        counts[(pair[0] - b'A') as usize * 26 + (pair[1] - b'A') as usize] += 1;
+    }
+    let total = letters.len().saturating_sub(1);
+    if total < 2 {
+        return 0.0;
+    }
+
+    let matches: usize = counts.iter().map(|&n| n * n.saturating_sub(1)).sum();
+    matches as f64 / (total * (total - 1)) as f64
+}
+
+// Pearson's chi-squared statistic of the letter counts against the expected
+// frequencies, which need not be normalized.
+pub fn chi_squared(text: &str, expected: &[f64; 26]) -> f64 {
+    let counts = letter_counts(text);
+    let total: usize = counts.iter().sum();
+    let expected_total: f64 = expected.iter().sum();
+    if total == 0 || expected_total <= 0.0 {
+        return 0.0;
+    }
+
+    counts
+        .iter()
+        .zip(expected)
+        .filter(|(_, &e)| e > 0.0)
+        .map(|(&observed, &e)| {
+            let e = e / expected_total * total as f64;
+            (observed as f64 - e).powi(2) / e
+        })
+        .sum()
+}
+
+// Letter counts normalized to sum to one.
+pub fn letter_frequencies(text: &str) -> [f64; 26] {
+    let counts = letter_counts(text);
+    let total = counts.iter().sum::<usize>().max(1) as f64;
+    counts.map(|c| c as f64 / total)
+}
+
+// Shannon entropy in bits of a letter distribution, which need not be
+// normalized. About 4.18 for English and log2(26) = 4.70 for uniform letters.
+pub fn distribution_entropy(distribution: &[f64; 26]) -> f64 {
+    let total: f64 = distribution.iter().sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    distribution
+        .iter()
+        .filter(|&&p| p > 0.0)
+        .map(|&p| {
+            let p = p / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+pub fn shannon_entropy(text: &str) -> f64 {
+    distribution_entropy(&letter_frequencies(text))
+}
+
+// Kullback-Leibler divergence in bits of the text's letter distribution from
+// the expected one. Expected letters with no mass are floored so a single
+// stray letter costs a large but finite penalty.
+pub fn kl_divergence(text: &str, expected: &[f64; 26]) -> f64 {
+    let observed = letter_frequencies(text);
+    let expected_total: f64 = expected.iter().sum();
+    if expected_total <= 0.0 {
+        return 0.0;
+    }
+    observed
+        .iter()
+        .zip(expected)
+        .filter(|(&p, _)| p > 0.0)
+        .map(|(&p, &q)| p * (p / (q / expected_total).max(1e-6)).log2())
+        .sum()
+}
+
+// Reads a letter distribution from `LETTER COUNT` lines, the monograms.txt
+// layout written by build-ngrams. Letters not listed get zero.
+pub fn load_distribution<P: AsRef<Path>>(path: P) -> io::Result<[f64; 26]> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut distribution = [0.0; 26];
+
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "line {}: expected 'LETTER COUNT', got '{}'",
+                    line_number + 1,
+                    line
+                ),
+            )
+        };
+        let mut fields = line.split_whitespace();
+        let (Some(letter), Some(count), None) = (fields.next(), fields.next(), fields.next())
+        else {
+            return Err(invalid());
+        };
+        let letter = letters(letter);
+        let count = count.parse::<f64>().map_err(|_| invalid())?;
+        if letter.len() != 1 || count < 0.0 {
+            return Err(invalid());
+        }
+        distribution[(letter[0] - b'A') as usize] += count;
+    }
+
+    if distribution.iter().sum::<f64>() <= 0.0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "letter distribution is empty",
+        ));
+    }
+    Ok(distribution)
+}
+
+// The text split into `period` columns, column i holding every letter at a
+// position congruent to i.
+pub fn columns(text: &str, period: usize) -> Vec<String> {
+    let period = period.max(1);
+    let mut columns = vec![String::new(); period];
+    for (i, &b) in letters(text).iter().enumerate() {
+        columns[i % period].push(b as char);
+    }
+    columns
+}
+
+// Mean index of coincidence of the columns for a given period. For a
+// periodic polyalphabetic cipher this jumps towards English at the period
+// and its multiples.
+pub fn periodic_ioc(text: &str, period: usize) -> f64 {
+    let columns = columns(text, period);
+    columns.iter().map(|c| index_of_coincidence(c)).sum::<f64>() / columns.len() as f64
+}
+
+pub fn periodic_ioc_table(text: &str, max_period: usize) -> Vec<(usize, f64)> {
+    (1..=max_period)
+        .map(|period| (period, periodic_ioc(text, period)))
+        .collect()
+}
+
+// Friedman's estimate of a polyalphabetic key length from the overall IoC.
+// Returns None when the IoC is at or below random, where the estimate has
+// no meaning.
+pub fn friedman_key_length(text: &str) -> Option<f64> {
+    let n = letters(text).len() as f64;
+    let ioc = index_of_coincidence(text);
+    let denominator = (n - 1.0) * ioc - RANDOM_IOC * n + ENGLISH_IOC;
+    if n < 2.0 || ioc <= RANDOM_IOC || denominator <= 0.0 {
+        return None;
+    }
+    Some((ENGLISH_IOC - RANDOM_IOC) * n / denominator)
+}
+
+// The kappa (self-coincidence) test: the fraction of positions where the
+// text matches itself shifted by `offset`. Peaks near 0.067 suggest the
+// offset is a multiple of the period.
+pub fn kappa(text: &str, offset: usize) -> f64 {
+    let letters = letters(text);
+    if offset == 0 || offset >= letters.len() {
+        return 0.0;
+    }
+    let overlap = letters.len() - offset;
+    let matches = (0..overlap)
+        .filter(|&i| letters[i] == letters[i + offset])
+        .count();
+    matches as f64 / overlap as f64
+}
+
+pub fn kappa_table(text: &str, max_offset: usize) -> Vec<(usize, f64)> {
+    (1..=max_offset)
+        .map(|offset| (offset, kappa(text, offset)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-12
+    }
+
+    #[test]
+    fn index_of_coincidence_counts_matching_pairs() {
+        // A twice, B three times, C once: (2*1 + 3*2) / (6*5).
+        assert!(close(index_of_coincidence("aab-bbc"), 8.0 / 30.0));
+        assert_eq!(index_of_coincidence("ABCDEFGHIJ"), 0.0);
+        assert_eq!(index_of_coincidence("A"), 0.0);
+        // AB, BA, AB, BA: (2*1 + 2*1) / (4*3).
+        assert!(close(digraph_ioc("ABABA"), 4.0 / 12.0));
+    }
+
+    #[test]
+    fn periodic_ioc_averages_the_columns() {
+        assert_eq!(columns("AB CD E", 2), vec!["ACE", "BD"]);
+        // Period 2 splits ABABAB into AAA and BBB.
+        assert_eq!(periodic_ioc("ABABAB", 2), 1.0);
+        // Period 1 is the whole text: (3*2 + 3*2) / (6*5).
+        assert!(close(periodic_ioc("ABABAB", 1), 12.0 / 30.0));
+        // Period 4 gives AA, BB, A and B; single letters count as zero.
+        assert_eq!(periodic_ioc("ABABAB", 4), 0.5);
+        let table = periodic_ioc_table("ABABAB", 3);
+        assert_eq!(
+            table.iter().map(|&(p, _)| p).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(table[1].1, 1.0);
+    }
+
+    #[test]
+    fn friedman_estimates_from_the_overall_ioc() {
+        // n = 6 and IoC = 8/30, so (0.0667 - 1/26) * 6 over
+        // 5 * 8/30 - 6/26 + 0.0667.
+        let expected =
+            (ENGLISH_IOC - RANDOM_IOC) * 6.0 / (5.0 * 8.0 / 30.0 - RANDOM_IOC * 6.0 + ENGLISH_IOC);
+        assert!(close(friedman_key_length("AABBBC").unwrap(), expected));
+        assert!(close(expected, 0.144903763708));
+        // No letter repeats, so the IoC is below random.
+        assert_eq!(friedman_key_length("ABCDEFGHIJ"), None);
+        assert_eq!(friedman_key_length("A"), None);
+    }
+
+    #[test]
+    fn kappa_compares_the_text_with_itself_shifted() {
+        assert_eq!(kappa("ABCABC", 3), 1.0);
+        assert_eq!(kappa("ABCABC", 1), 0.0);
+        // A=A at the first position, A against B at the second.
+        assert_eq!(kappa("AAB", 1), 0.5);
+        assert_eq!(kappa("ABC", 0), 0.0);
+        assert_eq!(kappa("ABC", 3), 0.0);
+        assert_eq!(kappa_table("ABCABC", 3), vec![(1, 0.0), (2, 0.0), (3, 1.0)]);
+    }
+
+    #[test]
+    fn distribution_measures() {
+        let mut two_letters = [0.0; 26];
+        two_letters[0] = 1.0;
+        two_letters[1] = 1.0;
+        // Expected 1.5 of each letter against 2 As and 1 B.
+        assert!(close(chi_squared("AAB", &two_letters), 2.0 * 0.25 / 1.5));
+        assert!(close(shannon_entropy("AABB"), 1.0));
+        assert!(close(distribution_entropy(&[1.0; 26]), 26f64.log2()));
+        assert!(close(kl_divergence("AB", &two_letters), 0.0));
+        assert!(close(kl_divergence("AB", &[1.0; 26]), 13f64.log2()));
+    }
+}
@@ -0,0 +1,347 @@
+// store.rs
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// One finished search: the command, the settings it ran with (the options
+// given plus the defaults the command filled in, such as the seed), how long
+// it took and the top candidates it printed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunRecord {
+    pub id: usize,
+    pub command: String,
+    // Unix seconds.
+    pub started: u64,
+    pub seconds: f64,
+    pub config: Vec<(String, String)>,
+    pub candidates: Vec<StoredCandidate>,
+}
+
+// A candidate as the store keeps it. `key` is whatever identifies the
+// candidate's key for its command: a keyword, a permutation or a layer stack.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredCandidate {
+    pub score: f64,
+    pub key: String,
+    pub alphabet: String,
+    pub plaintext: String,
+}
+
+impl RunRecord {
+    pub fn new(command: &str, config: Vec<(String, String)>) -> Self {
+        Self {
+            id: 0,
+            command: command.to_string(),
+            started: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            seconds: 0.0,
+            config,
+            candidates: Vec::new(),
+        }
+    }
+
+    // Records a setting, replacing any earlier value for the name.
+    pub fn set(&mut self, name: &str, value: impl ToString) {
+        let value = value.to_string();
+        match self.config.iter_mut().find(|(n, _)| n == name) {
+            Some(entry) => entry.1 = value,
+            None => self.config.push((name.to_string(), value)),
+        }
+    }
+
+    pub fn setting(&self, name: &str) -> Option<&str> {
+        self.config
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn add(&mut self, score: f64, key: &str, alphabet: &str, plaintext: &str) {
+        self.candidates.push(StoredCandidate {
+            score,
+            key: key.to_string(),
+            alphabet: alphabet.to_string(),
+            plaintext: plaintext.to_string(),
+        });
+    }
+
+    pub fn best(&self) -> Option<&StoredCandidate> {
+        self.candidates
+            .iter()
+            .max_by(|a, b| a.score.total_cmp(&b.score))
+    }
+}
+
+impl StoredCandidate {
+    // What makes two runs' candidates the same result.
+    fn identity(&self) -> (&str, &str, &str) {
+        (&self.key, &self.alphabet, &self.plaintext)
+    }
+}
+
+// A directory of runs, one `run-<id>.txt` of tab-separated lines per run.
+pub struct ResultStore {
+    dir: PathBuf,
+}
+
+impl ResultStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    // Saves the record under the next free id, which is written back into it.
+    // Scores are written in Rust's shortest round-trip form, so they read
+    // back exactly.
+    pub fn save(&self, record: &mut RunRecord) -> io::Result<usize> {
+        fs::create_dir_all(&self.dir)?;
+        let mut id = self.ids()?.into_iter().max().unwrap_or(0) + 1;
+        let mut file = loop {
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(self.path(id))
+            {
+                Ok(file) => break file,
+                // Another run took the id first.
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => id += 1,
+                Err(e) => return Err(e),
+            }
+        };
+        record.id = id;
+
+        let mut contents = String::from("# kryptos run\n");
+        contents.push_str(&format!("command\t{}\n", record.command));
+        contents.push_str(&format!("started\t{}\n", record.started));
+        contents.push_str(&format!("seconds\t{:?}\n", record.seconds));
+        for (name, value) in &record.config {
+            contents.push_str(&format!("config\t{}\t{}\n", name, value));
+        }
+        for candidate in &record.candidates {
+            contents.push_str(&format!(
+                "candidate\t{:?}\t{}\t{}\t{}\n",
+                candidate.score, candidate.key, candidate.alphabet, candidate.plaintext
+            ));
+        }
+        file.write_all(contents.as_bytes())?;
+        Ok(id)
+    }
+
+    pub fn load(&self, id: usize) -> io::Result<RunRecord> {
+        let contents = fs::read_to_string(self.path(id))?;
+        let mut record = RunRecord::new("", Vec::new());
+        record.id = id;
+
+        for (line_number, line) in contents.lines().enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("run {} line {}: unexpected '{}'", id, line_number + 1, line),
+                )
+            };
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                ["command", command] => record.command = command.to_string(),
+                ["started", started] => record.started = started.parse().map_err(|_| invalid())?,
+                ["seconds", seconds] => record.seconds = seconds.parse().map_err(|_| invalid())?,
+                ["config", name, value] => {
+                    record.config.push((name.to_string(), value.to_string()))
+                }
+                ["candidate", score, key, alphabet, plaintext] => record.add(
+                    score.parse().map_err(|_| invalid())?,
+                    key,
+                    alphabet,
+                    plaintext,
+                ),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(record)
+    }
+
+    // Every stored run, oldest first. A store that does not exist yet is empty.
+    pub fn runs(&self) -> io::Result<Vec<RunRecord>> {
+        let mut ids = self.ids()?;
+        ids.sort_unstable();
+        ids.into_iter().map(|id| self.load(id)).collect()
+    }
+
+    fn ids(&self) -> io::Result<Vec<usize>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut ids = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name();
+            if let Some(id) = name
+                .to_str()
+                .and_then(|n| n.strip_prefix("run-"))
+                .and_then(|n| n.strip_suffix(".txt"))
+                .and_then(|n| n.parse().ok())
+            {
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+
+    fn path(&self, id: usize) -> PathBuf {
+        self.dir.join(format!("run-{}.txt", id))
+    }
+}
+
+// How two runs' top candidates compare. Common candidates carry both scores,
+// which differ when the runs scored them differently.
+#[derive(Debug, Clone)]
+pub struct RunDiff<'a> {
+    pub only_first: Vec<&'a StoredCandidate>,
+    pub only_second: Vec<&'a StoredCandidate>,
+    pub common: Vec<(&'a StoredCandidate, f64)>,
+}
+
+pub fn diff<'a>(first: &'a RunRecord, second: &'a RunRecord) -> RunDiff<'a> {
+    let second_scores: HashMap<_, f64> = second
+        .candidates
+        .iter()
+        .map(|c| (c.identity(), c.score))
+        .collect();
+    let first_keys: HashSet<_> = first.candidates.iter().map(|c| c.identity()).collect();
+
+    let mut result = RunDiff {
+        only_first: Vec::new(),
+        only_second: Vec::new(),
+        common: Vec::new(),
+    };
+    for candidate in &first.candidates {
+        match second_scores.get(&candidate.identity()) {
+            Some(&score) => result.common.push((candidate, score)),
+            None => result.only_first.push(candidate),
+        }
+    }
+    result.only_second = second
+        .candidates
+        .iter()
+        .filter(|c| !first_keys.contains(&c.identity()))
+        .collect();
+    result
+}
+
+// Stored candidates with this key, and with this alphabet when one is given.
+// Keys and alphabets compare case-insensitively.
+pub fn find<'a>(
+    runs: &'a [RunRecord],
+    key: Option<&str>,
+    alphabet: Option<&str>,
+) -> Vec<(&'a RunRecord, &'a StoredCandidate)> {
+    let matches = |wanted: Option<&str>, value: &str| {
+        wanted.is_none_or(|wanted| wanted.eq_ignore_ascii_case(value))
+    };
+    runs.iter()
+        .flat_map(|run| run.candidates.iter().map(move |c| (run, c)))
+        .filter(|(_, c)| matches(key, &c.key) && matches(alphabet, &c.alphabet))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_store(name: &str) -> (PathBuf, ResultStore) {
+        let dir =
+            std::env::temp_dir().join(format!("kryptos-store-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        (dir.clone(), ResultStore::new(dir))
+    }
+
+    fn record(command: &str, candidates: &[(f64, &str, &str)]) -> RunRecord {
+        let mut record = RunRecord::new(
+            command,
+            vec![
+                ("enforce-cribs".to_string(), String::new()),
+                ("wordlist".to_string(), "words.txt".to_string()),
+            ],
+        );
+        record.set("seed", 42);
+        record.seconds = 1.0 / 3.0;
+        for &(score, key, alphabet) in candidates {
+            record.add(score, key, alphabet, &format!("{}PLAINTEXT", key));
+        }
+        record
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let (dir, store) = scratch_store("round-trip");
+        let mut first = record(
+            "sweep",
+            &[(0.1 + 0.2, "KRYPTOS", "KRYPTOSABC"), (-7.5, "BERLIN", "")],
+        );
+        let mut second = record("hill-climb", &[(f64::MIN_POSITIVE, "ABCDEF", "")]);
+
+        assert_eq!(store.save(&mut first).unwrap(), 1);
+        assert_eq!(store.save(&mut second).unwrap(), 2);
+        assert_eq!(store.load(1).unwrap(), first);
+        assert_eq!(store.runs().unwrap(), vec![first.clone(), second]);
+        assert_eq!(first.setting("seed"), Some("42"));
+        assert_eq!(first.best().unwrap().key, "KRYPTOS");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_store_has_no_runs() {
+        let (_, store) = scratch_store("missing");
+        assert!(store.runs().unwrap().is_empty());
+    }
+
+    #[test]
+    fn load_rejects_malformed_lines() {
+        let (dir, store) = scratch_store("malformed");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("run-1.txt"), "candidate\tnot a score\tK\tA\tP\n").unwrap();
+        assert_eq!(
+            store.load(1).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn diff_matches_candidates_on_key_alphabet_and_plaintext() {
+        let first = record("sweep", &[(3.0, "KRYPTOS", "A"), (2.0, "BERLIN", "A")]);
+        let second = record("sweep", &[(3.5, "KRYPTOS", "A"), (1.0, "BERLIN", "B")]);
+        let diff = diff(&first, &second);
+
+        assert_eq!(diff.common.len(), 1);
+        assert_eq!(diff.common[0].0.key, "KRYPTOS");
+        assert_eq!(diff.common[0].1, 3.5);
+        assert_eq!(diff.only_first, vec![&first.candidates[1]]);
+        assert_eq!(diff.only_second, vec![&second.candidates[1]]);
+    }
+
+    #[test]
+    fn find_ignores_case_and_filters_on_alphabet() {
+        let runs = vec![
+            record(
+                "sweep",
+                &[(3.0, "KRYPTOS", "ALPHA"), (2.0, "BERLIN", "ALPHA")],
+            ),
+            record("sweep", &[(1.0, "KRYPTOS", "BETA")]),
+        ];
+
+        assert_eq!(find(&runs, Some("kryptos"), None).len(), 2);
+        let found = find(&runs, Some("KRYPTOS"), Some("beta"));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1.score, 1.0);
+        assert_eq!(find(&runs, None, Some("ALPHA")).len(), 2);
+        assert!(find(&runs, Some("CLOCK"), None).is_empty());
+    }
+}
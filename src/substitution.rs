@@ -1,6 +1,8 @@
 // substitution.rs
 
+use crate::key_generation::keyed_alphabet;
 use std::collections::HashMap;
+use std::str::FromStr;
 
 pub fn monoalphabetic_substitution(text: &str, key: &str, alphabet: &str) -> String {
     let mut key_map = HashMap::new();
@@ -62,3 +64,235 @@ pub fn generate_shifted_alphabets(key: &str, alphabet: &str) -> Vec<String> {
         })
         .collect()
 }
+
+// The additive periodic ciphers, expressed as the relation between the
+// plaintext index p, ciphertext index c and key shift k in an n-letter
+// tableau.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CipherFamily {
+    Vigenere,        // c = p + k
+    Beaufort,        // c = k - p
+    VariantBeaufort, // c = p - k
+}
+
+impl CipherFamily {
+    pub const ALL: [CipherFamily; 3] = [
+        CipherFamily::Vigenere,
+        CipherFamily::Beaufort,
+        CipherFamily::VariantBeaufort,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CipherFamily::Vigenere => "vigenere",
+            CipherFamily::Beaufort => "beaufort",
+            CipherFamily::VariantBeaufort => "variant-beaufort",
+        }
+    }
+
+    pub fn key_shift(&self, p: usize, c: usize, n: usize) -> usize {
+        match self {
+            CipherFamily::Vigenere => (c + n - p) % n,
+            CipherFamily::Beaufort => (c + p) % n,
+            CipherFamily::VariantBeaufort => (p + n - c) % n,
+        }
+    }
+
+    pub fn encrypt_index(&self, p: usize, k: usize, n: usize) -> usize {
+        match self {
+            CipherFamily::Vigenere => (p + k) % n,
+            CipherFamily::Beaufort => (k + n - p) % n,
+            CipherFamily::VariantBeaufort => (p + n - k) % n,
+        }
+    }
+
+    pub fn decrypt_index(&self, c: usize, k: usize, n: usize) -> usize {
+        match self {
+            CipherFamily::Vigenere => (c + n - k) % n,
+            CipherFamily::Beaufort => (k + n - c) % n,
+            CipherFamily::VariantBeaufort => (c + k) % n,
+        }
+    }
+}
+
+impl FromStr for CipherFamily {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CipherFamily::ALL
+            .into_iter()
+            .find(|family| family.name() == s)
+            .ok_or_else(|| format!("unknown cipher family '{}'", s))
+    }
+}
+
+// A pair of 26-letter alphabets: the plaintext alphabet indexes the letter
+// being enciphered and the ciphertext alphabet supplies the shifted row.
+// Key letters are read from the ciphertext alphabet, so KRYPTOS-keyed
+// Quagmire III keys read the same way as the K1 and K2 keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tableau {
+    pub name: String,
+    plain: Vec<char>,
+    cipher: Vec<char>,
+    plain_positions: [usize; 26],
+    cipher_positions: [usize; 26],
+}
+
+impl Tableau {
+    pub fn new(name: &str, plain: &str, cipher: &str) -> Result<Self, String> {
+        Ok(Self {
+            name: name.to_string(),
+            plain: plain.chars().collect(),
+            cipher: cipher.chars().collect(),
+            plain_positions: alphabet_positions(plain)?,
+            cipher_positions: alphabet_positions(cipher)?,
+        })
+    }
+
+    pub fn straight() -> Self {
+        Self::new("straight", STRAIGHT_ALPHABET, STRAIGHT_ALPHABET).unwrap()
+    }
+
+    // Keyed plaintext alphabet, straight ciphertext alphabet.
+    pub fn quagmire1(keyword: &str) -> Self {
+        let keyed = keyed_alphabet(keyword);
+        Self::new(&format!("quagmire1:{}", keyword), &keyed, STRAIGHT_ALPHABET).unwrap()
+    }
+
+    // Straight plaintext alphabet, keyed ciphertext alphabet.
+    pub fn quagmire2(keyword: &str) -> Self {
+        let keyed = keyed_alphabet(keyword);
+        Self::new(&format!("quagmire2:{}", keyword), STRAIGHT_ALPHABET, &keyed).unwrap()
+    }
+
+    // The same keyed alphabet on both sides, as used for K1 and K2.
+    pub fn quagmire3(keyword: &str) -> Self {
+        let keyed = keyed_alphabet(keyword);
+        Self::new(&format!("quagmire3:{}", keyword), &keyed, &keyed).unwrap()
+    }
+
+    // Independently keyed plaintext and ciphertext alphabets.
+    pub fn quagmire4(plain_keyword: &str, cipher_keyword: &str) -> Self {
+        Self::new(
+            &format!("quagmire4:{}:{}", plain_keyword, cipher_keyword),
+            &keyed_alphabet(plain_keyword),
+            &keyed_alphabet(cipher_keyword),
+        )
+        .unwrap()
+    }
+
+    pub fn plain_alphabet(&self) -> String {
+        self.plain.iter().collect()
+    }
+
+    pub fn cipher_alphabet(&self) -> String {
+        self.cipher.iter().collect()
+    }
+
+    pub fn plain_index(&self, c: char) -> Option<usize> {
+        letter_offset(c).map(|i| self.plain_positions[i])
+    }
+
+    pub fn cipher_index(&self, c: char) -> Option<usize> {
+        letter_offset(c).map(|i| self.cipher_positions[i])
+    }
+
+    pub fn plain_letter(&self, index: usize) -> char {
+        self.plain[index % 26]
+    }
+
+    pub fn cipher_letter(&self, index: usize) -> char {
+        self.cipher[index % 26]
+    }
+
+    pub fn key_letter(&self, shift: usize) -> char {
+        self.cipher_letter(shift)
+    }
+
+    pub fn key_shift(&self, key_letter: char) -> Option<usize> {
+        self.cipher_index(key_letter)
+    }
+
+    pub fn encrypt_char(&self, family: CipherFamily, p: char, shift: usize) -> Option<char> {
+        let p = self.plain_index(p)?;
+        Some(self.cipher_letter(family.encrypt_index(p, shift, 26)))
+    }
+
+    pub fn decrypt_char(&self, family: CipherFamily, c: char, shift: usize) -> Option<char> {
+        let c = self.cipher_index(c)?;
+        Some(self.plain_letter(family.decrypt_index(c, shift, 26)))
+    }
+}
+
+// Parses the names the constructors give: `straight`, `quagmire1:KEYWORD`
+// through `quagmire3:KEYWORD`, and `quagmire4:PLAIN:CIPHER`.
+impl FromStr for Tableau {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        match parts.as_slice() {
+            ["straight"] => Ok(Tableau::straight()),
+            ["quagmire1", keyword] => Ok(Tableau::quagmire1(keyword)),
+            ["quagmire2", keyword] => Ok(Tableau::quagmire2(keyword)),
+            ["quagmire3", keyword] => Ok(Tableau::quagmire3(keyword)),
+            ["quagmire4", plain, cipher] => Ok(Tableau::quagmire4(plain, cipher)),
+            _ => Err(format!("unknown tableau '{}'", s)),
+        }
+    }
+}
+
+pub const STRAIGHT_ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+fn letter_offset(c: char) -> Option<usize> {
+    c.is_ascii_uppercase().then(|| (c as u8 - b'A') as usize)
+}
+
+fn alphabet_positions(alphabet: &str) -> Result<[usize; 26], String> {
+    let mut positions = [usize::MAX; 26];
+    for (i, c) in alphabet.chars().enumerate() {
+        match letter_offset(c) {
+            Some(offset) if i < 26 && positions[offset] == usize::MAX => positions[offset] = i,
+            _ => return Err(format!("'{}' is not a permutation of A-Z", alphabet)),
+        }
+    }
+    if positions.contains(&usize::MAX) {
+        return Err(format!("'{}' is not a permutation of A-Z", alphabet));
+    }
+    Ok(positions)
+}
+
+// Deciphers letter by letter with the key repeated over the text. Letters
+// outside the tableau pass through unchanged but still consume a key letter.
+pub fn periodic_decrypt(text: &str, key: &str, family: CipherFamily, tableau: &Tableau) -> String {
+    let shifts: Vec<usize> = key.chars().filter_map(|k| tableau.key_shift(k)).collect();
+    if shifts.is_empty() {
+        return text.to_string();
+    }
+
+    text.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            tableau
+                .decrypt_char(family, c, shifts[i % shifts.len()])
+                .unwrap_or(c)
+        })
+        .collect()
+}
+
+pub fn periodic_encrypt(text: &str, key: &str, family: CipherFamily, tableau: &Tableau) -> String {
+    let shifts: Vec<usize> = key.chars().filter_map(|k| tableau.key_shift(k)).collect();
+    if shifts.is_empty() {
+        return text.to_string();
+    }
+
+    text.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            tableau
+                .encrypt_char(family, c, shifts[i % shifts.len()])
+                .unwrap_or(c)
+        })
+        .collect()
+}
@@ -1,3 +1,12 @@
+use crate::annealing::{self, AnnealingSchedule, Cooling, SearchSpace};
+use crate::cribs::{crib_mask, Crib};
+use crate::decryption::Candidate;
+use crate::scorer::Scorer;
+use crate::seeding;
+use rand::Rng;
+use rayon::prelude::*;
+use std::str::FromStr;
+
 pub fn columnar_transposition(text: &str, key: &str) -> String {
     let key_len = key.len();
     let text_len = text.len();
@@ -21,25 +30,448 @@ pub fn columnar_transposition(text: &str, key: &str) -> String {
     result.into_iter().collect()
 }
 
+// Writes the text in rows of key.len() letters and reads it off down the
+// first column, up the second and so on. Only the key's length matters.
 pub fn route_transposition(text: &str, key: &str) -> String {
-    let key_len = key.len();
-    let text_len = text.len();
-    let mut result = vec![' '; text_len];
+    let width = key.len().max(1);
+    let length = text.chars().count();
+    let route: Vec<usize> = (0..width.min(length))
+        .flat_map(|column| {
+            let mut cells: Vec<usize> = (column..length).step_by(width).collect();
+            if column % 2 == 1 {
+                cells.reverse();
+            }
+            cells
+        })
+        .collect();
+    apply_permutation(text, &route)
+}
+
+// The permutation a columnar transposition applies: the text is written in
+// rows of order.len() letters and the columns are read off in `order`.
+// `columnar_transposition` reads its columns in the alphabetical order of
+// the key letters.
+pub fn columnar_permutation(length: usize, order: &[usize]) -> Vec<usize> {
+    let width = order.len();
+    order
+        .iter()
+        .filter(|&&column| column < width)
+        .flat_map(|&column| (column..length).step_by(width))
+        .collect()
+}
+
+pub fn columnar_decrypt(text: &str, order: &[usize]) -> String {
+    let length = text.chars().count();
+    let permutation = columnar_permutation(length, order);
+    if permutation.len() != length {
+        return text.to_string();
+    }
+    apply_permutation(text, &invert_permutation(&permutation))
+}
+
+// Reads the text through an explicit permutation: output[i] = text[permutation[i]].
+pub fn apply_permutation(text: &str, permutation: &[usize]) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    permutation.iter().map(|&i| chars[i]).collect()
+}
+
+pub fn invert_permutation(permutation: &[usize]) -> Vec<usize> {
+    let mut inverse = vec![0; permutation.len()];
+    for (i, &p) in permutation.iter().enumerate() {
+        inverse[p] = i;
+    }
+    inverse
+}
+
+pub fn is_permutation(permutation: &[usize]) -> bool {
+    let mut seen = vec![false; permutation.len()];
+    permutation
+        .iter()
+        .all(|&p| p < seen.len() && !std::mem::replace(&mut seen[p], true))
+}
+
+// Steps to the next permutation in lexicographic order, returning false
+// (and leaving the slice untouched) after the last one.
+pub fn next_permutation(permutation: &mut [usize]) -> bool {
+    let Some(pivot) = permutation.windows(2).rposition(|w| w[0] < w[1]) else {
+        return false;
+    };
+    let successor = permutation
+        .iter()
+        .rposition(|&p| p > permutation[pivot])
+        .unwrap();
+    permutation.swap(pivot, successor);
+    permutation[pivot + 1..].reverse();
+    true
+}
+
+pub fn parse_permutation(spec: &str) -> Result<Vec<usize>, String> {
+    let permutation = spec
+        .split(',')
+        .map(|s| {
+            s.trim()
+                .parse::<usize>()
+                .map_err(|e| format!("invalid index '{}': {}", s.trim(), e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if !is_permutation(&permutation) {
+        return Err(format!(
+            "not a permutation of 0..{}: {}",
+            permutation.len(),
+            spec
+        ));
+    }
+    Ok(permutation)
+}
+
+pub fn format_permutation(permutation: &[usize]) -> String {
+    permutation
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
 
-    let mut index: isize = 0;
-    let mut direction: isize = 1;
-    for (_i, c) in text.char_indices() {
-        if index >= 0 && (index as usize) < text_len {
-            result[index as usize] = c;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermutationMove {
+    Swap,
+    BlockMove,
+    ReverseSegment,
+}
+
+impl PermutationMove {
+    pub const ALL: [PermutationMove; 3] = [
+        PermutationMove::Swap,
+        PermutationMove::BlockMove,
+        PermutationMove::ReverseSegment,
+    ];
+}
+
+// How the cribs constrain a permutation: either the permuted text must show
+// the crib letters at the crib positions (a pure transposition), or the crib
+// positions must stay in place so a later positional substitution can still
+// line up with them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CribPinning {
+    Letters,
+    Positions,
+}
+
+impl FromStr for CribPinning {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "letters" => Ok(CribPinning::Letters),
+            "positions" => Ok(CribPinning::Positions),
+            other => Err(format!("unknown crib pinning '{}'", other)),
         }
-        index += direction * key_len as isize;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PermutationAnnealing {
+    pub iterations: usize,
+    pub initial_temperature: f64,
+    pub cooling_rate: f64,
+    pub restarts: usize,
+    pub pinning: CribPinning,
+    pub seed: u64,
+}
 
-        if index >= text_len as isize || index < 0 {
-            direction *= -1;
-            index += direction * key_len as isize;
-            index += direction;
+impl Default for PermutationAnnealing {
+    fn default() -> Self {
+        Self {
+            iterations: 200_000,
+            initial_temperature: 0.5,
+            cooling_rate: 0.99997,
+            restarts: 8,
+            pinning: CribPinning::Letters,
+            seed: seeding::random_seed(),
         }
     }
+}
 
-    result.into_iter().collect()
-}
\ No newline at end of file
+#[derive(Debug, Clone)]
+pub struct PermutationResult {
+    pub permutation: Vec<usize>,
+    pub plaintext: String,
+    pub score: f64,
+}
+
+// Permutations of the ciphertext that keep the crib positions pinned, as a
+// space for `annealing`.
+pub struct PermutationSearch {
+    cipher_chars: Vec<char>,
+    mask: Vec<Option<char>>,
+    free: Vec<usize>,
+}
+
+impl PermutationSearch {
+    pub fn new(ciphertext: &str, cribs: &[Crib], pinning: CribPinning) -> Self {
+        let cipher_chars: Vec<char> = ciphertext.chars().collect();
+        let mut mask = crib_mask(cribs, cipher_chars.len());
+        if pinning == CribPinning::Positions {
+            for (required, &c) in mask.iter_mut().zip(&cipher_chars) {
+                if required.is_some() {
+                    *required = Some(c);
+                }
+            }
+        }
+        let free = (0..mask.len()).filter(|&i| mask[i].is_none()).collect();
+
+        Self {
+            cipher_chars,
+            mask,
+            free,
+        }
+    }
+}
+
+impl SearchSpace for PermutationSearch {
+    type State = Vec<usize>;
+
+    fn random_state<R: Rng>(&self, rng: &mut R) -> Option<Vec<usize>> {
+        initial_permutation(&self.cipher_chars, &self.mask, rng)
+    }
+
+    fn propose<R: Rng>(&self, state: &Vec<usize>, rng: &mut R) -> Option<Vec<usize>> {
+        let mut proposal = state.clone();
+        let mv = PermutationMove::ALL[rng.gen_range(0..PermutationMove::ALL.len())];
+        propose_move(
+            &mut proposal,
+            &self.cipher_chars,
+            &self.mask,
+            &self.free,
+            mv,
+            rng,
+        )
+        .then_some(proposal)
+    }
+
+    fn plaintext(&self, state: &Vec<usize>) -> String {
+        state.iter().map(|&i| self.cipher_chars[i]).collect()
+    }
+
+    fn candidate(&self, state: &Vec<usize>, plaintext: String, score: f64) -> Candidate {
+        Candidate::new(
+            score,
+            plaintext,
+            0,
+            0,
+            0,
+            format_permutation(state),
+            String::new(),
+        )
+    }
+}
+
+// Searches for the permutation whose output scores best while keeping the
+// crib positions pinned. Returns None when the ciphertext cannot supply the
+// letters the cribs demand.
+pub fn anneal_permutation(
+    ciphertext: &str,
+    cribs: &[Crib],
+    config: &PermutationAnnealing,
+    scorer: &dyn Scorer,
+) -> Option<PermutationResult> {
+    let space = PermutationSearch::new(ciphertext, cribs, config.pinning);
+    let schedule = AnnealingSchedule {
+        initial_temperature: config.initial_temperature,
+        cooling: Cooling::Geometric {
+            rate: config.cooling_rate,
+        },
+        iterations: config.iterations,
+        chains: config.restarts,
+        seed: config.seed,
+    };
+
+    (0..config.restarts.max(1))
+        .into_par_iter()
+        .filter_map(|restart| {
+            let mut rng = seeding::stream(config.seed, restart);
+            annealing::anneal_chain(&space, &schedule, scorer, &mut rng, |_| {})
+        })
+        .map(|result| PermutationResult {
+            permutation: result.state,
+            plaintext: result.plaintext,
+            score: result.score,
+        })
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+}
+
+fn initial_permutation<R: Rng>(
+    cipher_chars: &[char],
+    mask: &[Option<char>],
+    rng: &mut R,
+) -> Option<Vec<usize>> {
+    let mut unused: Vec<usize> = (0..cipher_chars.len()).collect();
+    for i in (1..unused.len()).rev() {
+        unused.swap(i, rng.gen_range(0..=i));
+    }
+
+    let mut permutation = vec![usize::MAX; mask.len()];
+    for (position, required) in mask.iter().enumerate() {
+        if let Some(c) = required {
+            let source = unused.iter().position(|&i| cipher_chars[i] == *c)?;
+            permutation[position] = unused.swap_remove(source);
+        }
+    }
+    for slot in permutation.iter_mut().filter(|slot| **slot == usize::MAX) {
+        *slot = unused.pop()?;
+    }
+
+    Some(permutation)
+}
+
+fn fits(required: Option<char>, c: char) -> bool {
+    required.is_none_or(|r| r == c)
+}
+
+// Applies one random move of the given kind. Swaps may exchange pinned
+// positions when the letters still match their cribs; block moves and
+// reversals only rearrange the unpinned positions.
+fn propose_move<R: Rng>(
+    permutation: &mut [usize],
+    cipher_chars: &[char],
+    mask: &[Option<char>],
+    free: &[usize],
+    mv: PermutationMove,
+    rng: &mut R,
+) -> bool {
+    let n = permutation.len();
+    if n < 2 || free.len() < 2 {
+        return false;
+    }
+
+    match mv {
+        PermutationMove::Swap => {
+            for _ in 0..16 {
+                let i = rng.gen_range(0..n);
+                let j = rng.gen_range(0..n);
+                if i != j
+                    && fits(mask[i], cipher_chars[permutation[j]])
+                    && fits(mask[j], cipher_chars[permutation[i]])
+                {
+                    permutation.swap(i, j);
+                    return true;
+                }
+            }
+            false
+        }
+        PermutationMove::BlockMove => {
+            let mut values: Vec<usize> = free.iter().map(|&p| permutation[p]).collect();
+            let len = rng.gen_range(1..=(values.len() / 4).max(1));
+            let start = rng.gen_range(0..=values.len() - len);
+            let block: Vec<usize> = values.drain(start..start + len).collect();
+            let dest = rng.gen_range(0..=values.len());
+            values.splice(dest..dest, block);
+
+            for (&p, v) in free.iter().zip(values) {
+                permutation[p] = v;
+            }
+            true
+        }
+        PermutationMove::ReverseSegment => {
+            let a = rng.gen_range(0..free.len());
+            let b = rng.gen_range(0..free.len());
+            let (mut lo, mut hi) = (a.min(b), a.max(b));
+            if lo == hi {
+                return false;
+            }
+            while lo < hi {
+                permutation.swap(free[lo], free[hi]);
+                lo += 1;
+                hi -= 1;
+            }
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scorer::MonogramScorer;
+
+    const CIPHERTEXT: &str = "OBKRUOXOGHULBSOLIFBBWFLRVQQPRNGKSSOTWTQSJQSSEKZZWATJKLUDIAWINFBNYPVTTMZFPKWGDKZXTJCDIGKUHUAUEKCAR";
+
+    #[test]
+    fn route_snakes_down_and_up_the_columns() {
+        assert_eq!(route_transposition("ABCDEFGHI", "KEY"), "ADGHEBCFI");
+        assert_eq!(route_transposition("ABCDEFG", "KEY"), "ADGEBCF");
+        assert_eq!(route_transposition("AB", "KEY"), "AB");
+    }
+
+    #[test]
+    fn permutations_round_trip_through_their_inverse() {
+        let permutation = parse_permutation("2, 0,3,1").unwrap();
+        assert_eq!(apply_permutation("ABCD", &permutation), "CADB");
+        assert_eq!(
+            apply_permutation("CADB", &invert_permutation(&permutation)),
+            "ABCD"
+        );
+        assert_eq!(format_permutation(&permutation), "2,0,3,1");
+
+        assert!(parse_permutation("0,1,1").is_err());
+        assert!(parse_permutation("0,3,1").is_err());
+        assert!(parse_permutation("0,x").is_err());
+        assert!(is_permutation(&[]));
+    }
+
+    fn small_config(pinning: CribPinning) -> PermutationAnnealing {
+        PermutationAnnealing {
+            iterations: 2_000,
+            restarts: 2,
+            pinning,
+            seed: 7,
+            ..PermutationAnnealing::default()
+        }
+    }
+
+    #[test]
+    fn annealing_keeps_the_cribs_pinned() {
+        let cribs = [Crib::new(21, "EAST"), Crib::new(63, "BERLIN")];
+        let scorer = MonogramScorer::english();
+
+        let letters = anneal_permutation(
+            CIPHERTEXT,
+            &cribs,
+            &small_config(CribPinning::Letters),
+            &scorer,
+        )
+        .unwrap();
+        assert!(is_permutation(&letters.permutation));
+        assert_eq!(
+            apply_permutation(CIPHERTEXT, &letters.permutation),
+            letters.plaintext
+        );
+        assert_eq!(&letters.plaintext[21..25], "EAST");
+        assert_eq!(&letters.plaintext[63..69], "BERLIN");
+        assert_eq!(letters.score, scorer.score(&letters.plaintext));
+
+        let positions = anneal_permutation(
+            CIPHERTEXT,
+            &cribs,
+            &small_config(CribPinning::Positions),
+            &scorer,
+        )
+        .unwrap();
+        assert_eq!(&positions.plaintext[21..25], &CIPHERTEXT[21..25]);
+        assert_eq!(&positions.plaintext[63..69], &CIPHERTEXT[63..69]);
+    }
+
+    #[test]
+    fn annealing_fails_when_the_ciphertext_lacks_crib_letters() {
+        // The ciphertext has one E, the cribs need two.
+        let cribs = [Crib::new(0, "EE")];
+        let result = anneal_permutation(
+            "ABCDEFGH",
+            &cribs,
+            &small_config(CribPinning::Letters),
+            &MonogramScorer::english(),
+        );
+        assert!(result.is_none());
+    }
+}
@@ -0,0 +1,83 @@
+// common/mod.rs
+
+// Each test binary uses only some of these.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+pub const CIPHERTEXT: &str =
+    "OBKRUOXOGHULBSOLIFBBWFLRVQQPRNGKSSOTWTQSJQSSEKZZWATJKLUDIAWINFBNYPVTTMZFPKWGDKZXTJCDIGKUHUAUEKCAR";
+
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("kryptos-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// 100 six-letter words, enough for two alphabet chunks under each removal
+// hypothesis.
+pub fn wordlist() -> Vec<String> {
+    let mut state: u64 = 12345;
+    (0..100)
+        .map(|_| {
+            (0..6)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    (b'A' + (state >> 33) as u8 % 26) as char
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn write_inputs(dir: &Path) {
+    fs::write(dir.join("ciphertext.txt"), CIPHERTEXT).unwrap();
+    fs::write(dir.join("words.txt"), wordlist().join("\n")).unwrap();
+}
+
+pub fn run(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_kryptos_rs"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+// A sweep over the inputs of `write_inputs`, scored on letter frequencies
+// to keep it quick. Runs are stored under the directory.
+pub fn sweep(dir: &Path, args: &[&str]) -> Output {
+    let base = [
+        "sweep",
+        "--ciphertext",
+        "ciphertext.txt",
+        "--wordlist",
+        "words.txt",
+        "--keyword-length",
+        "6",
+        "--scorer",
+        "monogram",
+    ];
+    run(dir, &[&base[..], args].concat())
+}
+
+pub fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn top_candidates(output: &Output) -> Vec<String> {
+    stdout(output)
+        .lines()
+        .filter(|line| line.starts_with("Score:"))
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,67 @@
+// result_store.rs
+
+mod common;
+
+use common::{run, scratch_dir, stdout, sweep, wordlist, write_inputs};
+use kryptos_rs::substitution;
+use std::fs;
+
+#[test]
+fn tested_reports_tried_rejected_and_changed_wordlists() {
+    let dir = scratch_dir("tested");
+    write_inputs(&dir);
+    let keyword = wordlist()[0].clone();
+
+    // Run 1 decrypts every combination; run 2 rejects most of them on the
+    // cribs.
+    assert!(stdout(&sweep(&dir, &[])).contains("Stored as run 1"));
+    assert!(stdout(&sweep(&dir, &["--enforce-cribs"])).contains("Stored as run 2"));
+
+    let runs = stdout(&run(&dir, &["runs", "--command", "sweep"]));
+    assert!(runs.contains("Run 1: sweep"));
+    assert!(runs.contains("--enforce-cribs"));
+
+    let tested = stdout(&run(&dir, &["tested", "--key", &keyword]));
+    assert!(tested.contains("Run 1 (sweep) tried it with the 6-letter words of words.txt"));
+    assert!(tested.contains("Run 2 (sweep) rejected it on the cribs"));
+
+    // The alphabet a wordlist entry keys from the sweep's base alphabet.
+    let base = "KRYPTOSABCDEFGHIJLNGHIJLMNQUVWXZ";
+    let alphabet = substitution::polyalphabetic_substitution(base, &wordlist()[1], base);
+    let tested = stdout(&run(&dir, &["tested", "--alphabet", &alphabet]));
+    assert!(tested.contains("Run 1 (sweep) tried it"));
+    let tested = stdout(&run(
+        &dir,
+        &["tested", "--key", &keyword, "--alphabet", &alphabet],
+    ));
+    assert!(tested.contains("Run 1 (sweep) tried it"));
+
+    let tested = stdout(&run(&dir, &["tested", "--key", "QQQQQQ"]));
+    assert!(tested.contains("Not tried in any of 2 stored runs."));
+
+    fs::write(dir.join("words.txt"), "QQQQQQ\n").unwrap();
+    let tested = stdout(&run(&dir, &["tested", "--key", "QQQQQQ"]));
+    assert!(tested.contains("Run 1 (sweep): cannot check, its wordlist words.txt has changed"));
+    assert!(tested.contains("Not tried in any of 2 stored runs."));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn diff_runs_compares_settings_and_candidates() {
+    let dir = scratch_dir("diff-runs");
+    write_inputs(&dir);
+    stdout(&sweep(&dir, &[]));
+    stdout(&sweep(&dir, &["--remove", "every:7:0"]));
+
+    let diff = stdout(&run(&dir, &["diff-runs", "--runs", "1,2"]));
+    assert!(diff.contains("Run 1 (sweep) vs run 2 (sweep)"));
+    assert!(diff.contains("--remove: (unset) -> every:7:0"));
+    assert!(diff.contains("Only in run 1 (250):"));
+    assert!(diff.contains("Only in run 2 (250):"));
+
+    let same = stdout(&run(&dir, &["diff-runs", "--runs", "1,1"]));
+    assert!(same.contains("In both (250):"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
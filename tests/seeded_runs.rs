@@ -0,0 +1,50 @@
+// seeded_runs.rs
+
+mod common;
+
+use common::{run, scratch_dir, stdout, write_inputs};
+use std::fs;
+use std::path::Path;
+
+// Each randomized command, sized to finish quickly.
+const SEARCHES: &[&str] = &[
+    "hill-climb --key-length 5 --iterations 500 --restarts 3",
+    "anneal --key-length 5 --iterations 500 --chains 3",
+    "anneal-transposition --iterations 300 --restarts 2 --pin positions",
+    "evolve --population 20 --generations 5",
+    "beam --layers columnar,monoalphabetic --max-width 4 --beam-width 3 --iterations 300 --restarts 2",
+    "quagmire --key-length 4 --iterations 300 --restarts 2",
+    "identify --samples 5",
+    "calibrate --samples 50",
+];
+
+fn search(dir: &Path, command: &str, seed: &str) -> String {
+    let mut args: Vec<&str> = command.split_whitespace().collect();
+    args.extend([
+        "--ciphertext",
+        "ciphertext.txt",
+        "--scorer",
+        "monogram",
+        "--no-store",
+        "--seed",
+        seed,
+    ]);
+    stdout(&run(dir, &args))
+}
+
+#[test]
+fn the_same_seed_gives_the_same_output() {
+    let dir = scratch_dir("seeded-runs");
+    write_inputs(&dir);
+
+    for command in SEARCHES {
+        let first = search(&dir, command, "42");
+        assert!(first.lines().any(|line| line == "Seed: 42"), "{}", command);
+        assert_eq!(first, search(&dir, command, "42"), "{}", command);
+    }
+    assert_ne!(
+        search(&dir, SEARCHES[0], "42"),
+        search(&dir, SEARCHES[0], "43")
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
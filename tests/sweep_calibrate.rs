@@ -0,0 +1,94 @@
+// sweep_calibrate.rs
+
+mod common;
+
+use common::{run, scratch_dir, stdout, sweep, write_inputs};
+use std::collections::HashMap;
+use std::fs;
+
+fn field(line: &str, name: &str) -> f64 {
+    let start = line.find(name).unwrap() + name.len();
+    line[start..]
+        .split([',', ' '])
+        .find(|s| !s.is_empty())
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+#[test]
+fn each_removal_hypothesis_is_calibrated_on_its_own_text() {
+    let dir = scratch_dir("calibrate");
+    write_inputs(&dir);
+    let output = stdout(&sweep(
+        &dir,
+        &[
+            "--remove",
+            "every:7:0;at:5",
+            "--calibrate",
+            "200",
+            "--seed",
+            "3",
+        ],
+    ));
+
+    // Mean and standard deviation of each hypothesis' baseline.
+    let mut baselines = HashMap::new();
+    let mut removed = None;
+    for line in output.lines() {
+        if let Some(pattern) = line.strip_prefix("Removed: ") {
+            removed = Some(pattern.to_string());
+        } else if line.starts_with("Baseline over 200 random texts (seed 3)") {
+            let stats = (field(line, "mean "), field(line, "std dev "));
+            baselines.insert(removed.take().unwrap(), stats);
+        }
+    }
+    assert_eq!(baselines.len(), 2);
+    assert_ne!(baselines["every:7:0"], baselines["at:5"]);
+
+    // Every candidate's z-score is measured against its own hypothesis.
+    let lines: Vec<&str> = output.lines().collect();
+    let mut checked = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if !line.starts_with("Score:") {
+            continue;
+        }
+        let pattern = line
+            .split("Removed: ")
+            .nth(1)
+            .unwrap()
+            .split(',')
+            .next()
+            .unwrap();
+        let (mean, std_dev) = baselines[pattern];
+        let z = field(lines[i + 1], "z: ");
+        assert!((z - (field(line, "Score: ") - mean) / std_dev).abs() < 1e-3);
+        checked += 1;
+    }
+    assert_eq!(checked, 250);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn calibrate_rejects_an_alphabet_without_letters() {
+    let dir = scratch_dir("calibrate-alphabet");
+    write_inputs(&dir);
+    for alphabet in ["--alphabet=", "--alphabet=123"] {
+        let output = run(
+            &dir,
+            &[
+                "calibrate",
+                "--ciphertext",
+                "ciphertext.txt",
+                alphabet,
+                "--samples",
+                "10",
+            ],
+        );
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("at least one letter"));
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
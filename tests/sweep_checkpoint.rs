@@ -0,0 +1,59 @@
+// sweep_checkpoint.rs
+
+mod common;
+
+use common::{scratch_dir, stdout, sweep, top_candidates, write_inputs};
+use kryptos_rs::checkpoint::SweepCheckpoint;
+use std::fs;
+
+#[test]
+fn resumed_sweep_matches_uninterrupted_sweep() {
+    let dir = scratch_dir("resume");
+    write_inputs(&dir);
+    let remove = ["--remove", "every:7:0;at:5"];
+
+    let straight = top_candidates(&sweep(&dir, &remove));
+    assert_eq!(straight.len(), 250);
+
+    // Stop after every chunk and resume until the sweep finishes.
+    let slice = [
+        &remove[..],
+        &["--checkpoint", "sweep.ckpt", "--time-limit", "0"],
+    ]
+    .concat();
+    let mut output = sweep(&dir, &slice);
+    let mut slices = 1;
+    while stdout(&output).contains("Stopped at the time limit") {
+        let checkpoint = SweepCheckpoint::load(dir.join("sweep.ckpt")).unwrap();
+        assert!(checkpoint.removal < 2);
+        output = sweep(&dir, &[&slice[..], &["--resume"]].concat());
+        slices += 1;
+    }
+    assert!(slices > 4, "sweep finished in {} slices", slices);
+    assert_eq!(top_candidates(&output), straight);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn resume_refuses_a_checkpoint_from_another_sweep() {
+    let dir = scratch_dir("refuse");
+    write_inputs(&dir);
+    let checkpoint = ["--checkpoint", "sweep.ckpt", "--time-limit", "0"];
+    stdout(&sweep(
+        &dir,
+        &[&checkpoint[..], &["--remove", "every:7:0"]].concat(),
+    ));
+
+    for other in [
+        &["--remove", "every:5:1"][..],
+        &["--remove", "every:7:0", "--no-cribs"][..],
+        &["--remove", "every:7:0", "--reference", "uniform"][..],
+    ] {
+        let resumed = sweep(&dir, &[&checkpoint[..], other, &["--resume"]].concat());
+        assert!(!resumed.status.success(), "resumed with {:?}", other);
+        assert!(String::from_utf8_lossy(&resumed.stderr).contains("different sweep"));
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}